@@ -0,0 +1,51 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Context menu. Loaded in every page as a userscript.
+//
+// Servo doesn't tell the embedder what is under the mouse, so the page
+// hit-tests the right click itself and reports the link and image URLs
+// through a servoshell://contextmenu URL. The token is replaced by
+// ServoShell when installing the script, see hints.js.
+
+(function() {
+  var TOKEN = "__SERVOSHELL_TOKEN__";
+  if (document.currentScript) {
+    document.currentScript.parentNode.removeChild(document.currentScript);
+  }
+  if (window.__servoshellContextMenu) {
+    return;
+  }
+  window.__servoshellContextMenu = true;
+
+  function closest(element, test) {
+    while (element && element.nodeType == 1) {
+      if (test(element)) {
+        return element;
+      }
+      element = element.parentNode;
+    }
+    return null;
+  }
+
+  window.addEventListener("mousedown", function(event) {
+    if (event.button != 2) {
+      return;
+    }
+    var link = closest(event.target, function(e) {
+      return (e.localName == "a" || e.localName == "area") && e.href;
+    });
+    var image = closest(event.target, function(e) {
+      return e.localName == "img" && e.src;
+    });
+    var params = [];
+    if (link) {
+      params.push("link=" + encodeURIComponent(link.href));
+    }
+    if (image) {
+      params.push("image=" + encodeURIComponent(image.src));
+    }
+    window.location.href = "servoshell://contextmenu?" + params.join("&") + "&token=" + TOKEN;
+  }, true);
+})();
@@ -0,0 +1,160 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Link hints. Loaded in every page as a userscript.
+//
+// ServoShell can't call into the page, so it triggers the hints with a key
+// combination that can't be typed (Ctrl+Alt+Shift+Meta and F, N or Y).
+// The result goes back to ServoShell through a servoshell://hints/ URL.
+//
+// ServoShell replaces the token below when installing the script. It proves
+// the request comes from this script, pages can't open tabs or write to the
+// clipboard through the same URLs. Don't pass it to anything the page can
+// override (Array.prototype.join…), and remove the script from the DOM.
+
+(function() {
+  var TOKEN = "__SERVOSHELL_TOKEN__";
+  if (document.currentScript) {
+    document.currentScript.parentNode.removeChild(document.currentScript);
+  }
+  if (window.__servoshellHints) {
+    return;
+  }
+  window.__servoshellHints = true;
+
+  var ALPHABET = "asdfghjkl";
+  var SELECTOR = "a[href], area[href], button, input, select, textarea, summary, label, " +
+                 "[onclick], [role=button], [role=link], [tabindex]";
+  var ACTIONS = { 70: "click", 78: "open", 89: "copy" };
+
+  var overlay = null;
+  var hints = [];
+  var action = null;
+  var typed = "";
+
+  function labels(count) {
+    var length = 1;
+    while (Math.pow(ALPHABET.length, length) < count) {
+      length++;
+    }
+    var result = [];
+    for (var i = 0; i < count; i++) {
+      var label = "";
+      var n = i;
+      for (var j = 0; j < length; j++) {
+        label = ALPHABET[n % ALPHABET.length] + label;
+        n = Math.floor(n / ALPHABET.length);
+      }
+      result.push(label);
+    }
+    return result;
+  }
+
+  function isVisible(rect) {
+    return rect.width > 0 && rect.height > 0 &&
+           rect.bottom > 0 && rect.right > 0 &&
+           rect.top < window.innerHeight && rect.left < window.innerWidth;
+  }
+
+  function show(newAction) {
+    hide();
+    var elements = Array.prototype.slice.call(document.querySelectorAll(SELECTOR));
+    if (newAction != "click") {
+      elements = elements.filter(function(e) { return e.href; });
+    }
+    var targets = [];
+    elements.forEach(function(element) {
+      var rect = element.getBoundingClientRect();
+      if (isVisible(rect)) {
+        targets.push({ element: element, rect: rect });
+      }
+    });
+    if (targets.length == 0) {
+      return;
+    }
+    action = newAction;
+    typed = "";
+    overlay = document.createElement("div");
+    overlay.style.cssText = "position: fixed; top: 0; left: 0; z-index: 2147483647;";
+    var names = labels(targets.length);
+    hints = targets.map(function(target, i) {
+      var span = document.createElement("span");
+      span.textContent = names[i];
+      span.style.cssText = "position: fixed; padding: 0 2px; font: bold 11px monospace;" +
+                           "color: black; background: #ffd76e; border: 1px solid #c38a22;" +
+                           "text-transform: uppercase;" +
+                           "left: " + Math.max(0, target.rect.left) + "px;" +
+                           "top: " + Math.max(0, target.rect.top) + "px;";
+      overlay.appendChild(span);
+      return { label: names[i], span: span, element: target.element, rect: target.rect };
+    });
+    document.documentElement.appendChild(overlay);
+  }
+
+  function hide() {
+    if (overlay) {
+      overlay.parentNode.removeChild(overlay);
+    }
+    overlay = null;
+    hints = [];
+    action = null;
+  }
+
+  function report(path, params) {
+    var query = Object.keys(params).map(function(key) {
+      return key + "=" + encodeURIComponent(params[key]);
+    }).join("&");
+    window.location.href = "servoshell://hints/" + path + "?" + query + "&token=" + TOKEN;
+  }
+
+  function select(hint) {
+    var selectedAction = action;
+    hide();
+    if (selectedAction == "click") {
+      var rect = hint.element.getBoundingClientRect();
+      report("click", { x: rect.left + rect.width / 2, y: rect.top + rect.height / 2 });
+    } else {
+      report(selectedAction, { url: hint.element.href });
+    }
+  }
+
+  window.addEventListener("keydown", function(event) {
+    if (event.ctrlKey && event.altKey && event.shiftKey && event.metaKey &&
+        ACTIONS[event.keyCode]) {
+      show(ACTIONS[event.keyCode]);
+      event.preventDefault();
+      event.stopPropagation();
+      return;
+    }
+    if (!overlay) {
+      return;
+    }
+    event.preventDefault();
+    event.stopPropagation();
+    if (event.keyCode == 27) {
+      hide();
+      return;
+    }
+    if (event.keyCode == 8) {
+      typed = typed.slice(0, -1);
+    } else {
+      var c = String.fromCharCode(event.keyCode).toLowerCase();
+      if (ALPHABET.indexOf(c) == -1) {
+        hide();
+        return;
+      }
+      typed += c;
+    }
+    var matches = hints.filter(function(hint) {
+      var match = hint.label.indexOf(typed) == 0;
+      hint.span.style.display = match ? "" : "none";
+      return match;
+    });
+    if (matches.length == 0) {
+      hide();
+    } else if (matches.length == 1 && matches[0].label == typed) {
+      select(matches[0]);
+    }
+  }, true);
+})();
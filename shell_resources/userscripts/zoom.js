@@ -0,0 +1,66 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// Smart zoom. Loaded in every page as a userscript.
+//
+// On double tap, ServoShell moves the pointer to the tap position and sends
+// Ctrl+Alt+Shift+Meta+Z. This finds the block under the pointer and sends
+// its position and width back through a servoshell://zoom URL. The token is replaced by
+// ServoShell when installing the script, see hints.js.
+
+(function() {
+  var TOKEN = "__SERVOSHELL_TOKEN__";
+  if (document.currentScript) {
+    document.currentScript.parentNode.removeChild(document.currentScript);
+  }
+  if (window.__servoshellZoom) {
+    return;
+  }
+  window.__servoshellZoom = true;
+
+  var MIN_WIDTH = 100;
+  var BLOCKS = ["block", "list-item", "table", "flex", "grid"];
+
+  var x = 0;
+  var y = 0;
+
+  function track(event) {
+    var point = event.touches ? event.touches[0] : event;
+    if (point) {
+      x = point.clientX;
+      y = point.clientY;
+    }
+  }
+  window.addEventListener("mousemove", track, true);
+  window.addEventListener("touchstart", track, true);
+
+  function findBlock(element) {
+    while (element && element != document.documentElement) {
+      var style = window.getComputedStyle(element);
+      var width = element.getBoundingClientRect().width;
+      if (BLOCKS.indexOf(style.display) != -1 && width >= MIN_WIDTH) {
+        return element;
+      }
+      element = element.parentElement;
+    }
+    return null;
+  }
+
+  window.addEventListener("keydown", function(event) {
+    if (!(event.ctrlKey && event.altKey && event.shiftKey && event.metaKey &&
+          event.keyCode == 90)) {
+      return;
+    }
+    event.preventDefault();
+    event.stopPropagation();
+    var block = findBlock(document.elementFromPoint(x, y));
+    if (!block) {
+      return;
+    }
+    var rect = block.getBoundingClientRect();
+    window.location.href = "servoshell://zoom?width=" + rect.width +
+                           "&left=" + rect.left + "&top=" + rect.top +
+                           "&viewport=" + window.innerWidth + "&token=" + TOKEN;
+  }, true);
+})();
@@ -0,0 +1,327 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use getopts::Options;
+use log::LogLevelFilter;
+use logs;
+use servo::ServoUrl;
+use state::DebugOptions;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const PKG_VERSION: &'static str = env!("CARGO_PKG_VERSION");
+const PKG_NAME: &'static str = env!("CARGO_PKG_NAME");
+
+pub struct CommandLine {
+    pub urls: Vec<String>,
+    pub size: Option<(u32, u32)>,
+    pub position: Option<(i32, i32)>,
+    pub fullscreen: bool,
+    // None in private mode
+    pub profile: Option<PathBuf>,
+    pub log_level: LogLevelFilter,
+    // Levels of some targets, override log_level
+    pub log_filters: Vec<(String, LogLevelFilter)>,
+    pub log_capacity: usize,
+    pub log_file: bool,
+    pub prefs: Vec<String>,
+    pub debug: Vec<String>,
+    pub devtools_port: Option<u16>,
+    pub remote: bool,
+}
+
+impl CommandLine {
+    /// The options to start another ServoShell the same way, without the
+    /// URLs and the layout debug options.
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some((width, height)) = self.size {
+            args.push("--size".to_owned());
+            args.push(format!("{}x{}", width, height));
+        }
+        if let Some((x, y)) = self.position {
+            args.push("--position".to_owned());
+            args.push(format!("{},{}", x, y));
+        }
+        if self.fullscreen {
+            args.push("--fullscreen".to_owned());
+        }
+        match self.profile {
+            Some(ref profile) => {
+                args.push("--profile".to_owned());
+                args.push(profile.display().to_string());
+            }
+            None => args.push("--private".to_owned()),
+        }
+        args.push("--log-level".to_owned());
+        args.push(self.log_level.to_string());
+        for &(ref target, level) in &self.log_filters {
+            args.push("--log-filter".to_owned());
+            args.push(format!("{}={}", target, level));
+        }
+        args.push("--log-capacity".to_owned());
+        args.push(self.log_capacity.to_string());
+        if self.log_file {
+            args.push("--log-file".to_owned());
+        }
+        for pref in &self.prefs {
+            args.push("--pref".to_owned());
+            args.push(pref.clone());
+        }
+        if let Some(port) = self.devtools_port {
+            args.push("--devtools".to_owned());
+            args.push(port.to_string());
+        }
+        if self.remote {
+            args.push("--remote".to_owned());
+        }
+        args
+    }
+}
+
+fn options() -> Options {
+    let mut opts = Options::new();
+    opts.optflag("h", "help", "Print this message and exit");
+    opts.optflag("", "version", "Print the version and exit");
+    opts.optopt("", "size", "Initial size of the window", "WIDTHxHEIGHT");
+    opts.optopt("", "position", "Initial position of the window", "X,Y");
+    opts.optflag("", "fullscreen", "Start in fullscreen mode");
+    opts.optopt("", "profile", "Profile directory (default: ~/.servoshell)", "DIR");
+    opts.optflag("", "private", "Don't read or write any profile data");
+    opts.optopt("",
+                "log-level",
+                "Maximum log level: off, error, warn, info, debug or trace (default: info)",
+                "LEVEL");
+    opts.optmulti("",
+                  "log-filter",
+                  "Log level of a target and its children, like script=debug",
+                  "TARGET=LEVEL");
+    opts.optopt("",
+                "log-capacity",
+                &format!("Number of logs kept in memory (default: {})", logs::DEFAULT_CAPACITY),
+                "COUNT");
+    opts.optflag("", "log-file", "Also write the logs to servoshell.log in the profile");
+    opts.optmulti("", "pref", "Set a Servo preference", "NAME=VALUE");
+    opts.optmulti("Z",
+                  "debug",
+                  "Enable a layout debug option: show-fragment-borders, \
+                   parallel-display-list-building, show-parallel-layout or show-tiles-borders",
+                  "OPTION");
+    opts.optopt("", "devtools", "Start the remote devtools server on a port", "PORT");
+    opts.optflag("",
+                 "remote",
+                 "Accept commands on servoshell.sock, in $XDG_RUNTIME_DIR or the profile");
+    opts
+}
+
+/// Parse the command line. Print the usage or the version and exit if
+/// asked to, or print an error and exit on invalid input.
+pub fn parse() -> CommandLine {
+    // Finder adds a process serial number when launching the app bundle
+    // on older macOS versions
+    let args: Vec<String> = env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("-psn_"))
+        .collect();
+    let opts = options();
+
+    let matches = match opts.parse(&args) {
+        Ok(matches) => matches,
+        Err(f) => exit_with_error(&f.to_string()),
+    };
+
+    if matches.opt_present("help") {
+        let brief = format!("Usage: {} [options] [URL...]", PKG_NAME);
+        print!("{}", opts.usage(&brief));
+        process::exit(0);
+    }
+
+    if matches.opt_present("version") {
+        println!("{} {}", PKG_NAME, PKG_VERSION);
+        process::exit(0);
+    }
+
+    let size = matches
+        .opt_str("size")
+        .map(|s| parse_pair(&s, 'x').unwrap_or_else(|| exit_with_error("Invalid --size")));
+
+    let position = matches
+        .opt_str("position")
+        .map(|s| parse_pair(&s, ',').unwrap_or_else(|| exit_with_error("Invalid --position")));
+
+    let log_level = match matches.opt_str("log-level") {
+        Some(level) => {
+            level
+                .parse()
+                .unwrap_or_else(|_| exit_with_error(&format!("Invalid log level: {}", level)))
+        }
+        None => LogLevelFilter::Info,
+    };
+
+    let log_filters = matches
+        .opt_strs("log-filter")
+        .iter()
+        .map(|filter| {
+                 let mut split = filter.splitn(2, '=');
+                 let target = split.next().unwrap_or("").trim();
+                 let level = split.next().and_then(|level| level.trim().parse().ok());
+                 match level {
+                     Some(level) if !target.is_empty() => (target.to_owned(), level),
+                     _ => exit_with_error(&format!("Invalid log filter: {}", filter)),
+                 }
+             })
+        .collect();
+
+    let log_capacity = matches
+        .opt_str("log-capacity")
+        .map_or(logs::DEFAULT_CAPACITY, |s| {
+            s.parse()
+                .unwrap_or_else(|_| exit_with_error("Invalid --log-capacity"))
+        });
+
+    if matches.opt_present("private") && matches.opt_present("profile") {
+        exit_with_error("--private and --profile can't be used together");
+    }
+
+    let profile = if matches.opt_present("private") {
+        None
+    } else {
+        let dir = matches
+            .opt_str("profile")
+            .map(PathBuf::from)
+            .or_else(|| env::home_dir().map(|p| p.join(".servoshell")));
+        if let Some(ref dir) = dir {
+            if let Err(e) = fs::create_dir_all(dir) {
+                exit_with_error(&format!("Can't create profile {}: {}", dir.display(), e));
+            }
+        }
+        dir
+    };
+
+    let log_file = matches.opt_present("log-file");
+    if log_file && profile.is_none() {
+        exit_with_error("--log-file can't be used in private mode");
+    }
+
+    let prefs = matches.opt_strs("pref");
+
+    let debug = matches.opt_strs("debug");
+    for name in &debug {
+        if DebugOptions::default().enable(name).is_err() {
+            exit_with_error(&format!("Unknown debug option: {}", name));
+        }
+    }
+
+    let devtools_port = matches
+        .opt_str("devtools")
+        .map(|s| s.parse().unwrap_or_else(|_| exit_with_error("Invalid --devtools")));
+
+    let urls = matches
+        .free
+        .iter()
+        .map(|arg| {
+                 parse_url(arg).unwrap_or_else(|| exit_with_error(&format!("Invalid URL: {}", arg)))
+             })
+        .collect();
+
+    CommandLine {
+        urls,
+        size,
+        position,
+        fullscreen: matches.opt_present("fullscreen"),
+        profile,
+        log_level,
+        log_filters,
+        log_capacity,
+        log_file,
+        prefs,
+        debug,
+        devtools_port,
+        remote: matches.opt_present("remote"),
+    }
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}: {}", PKG_NAME, message);
+    eprintln!("Try '{} --help' for more information.", PKG_NAME);
+    process::exit(1);
+}
+
+fn parse_pair<T: ::std::str::FromStr>(s: &str, separator: char) -> Option<(T, T)> {
+    let mut split = s.splitn(2, separator);
+    let a = split.next().and_then(|a| a.trim().parse().ok());
+    let b = split.next().and_then(|b| b.trim().parse().ok());
+    match (a, b) {
+        (Some(a), Some(b)) => Some((a, b)),
+        _ => None,
+    }
+}
+
+/// Accept paths to local files, full URLs and host names.
+pub fn parse_url(arg: &str) -> Option<String> {
+    // Files first: C:\file.html would parse as a URL with a c: scheme
+    Path::new(arg)
+        .canonicalize()
+        .ok()
+        .and_then(|path| ServoUrl::from_file_path(&path).ok())
+        .or_else(|| ServoUrl::parse(arg).ok())
+        .or_else(|| parse_host(arg))
+        .map(|url| url.into_string())
+}
+
+/// Like the urlbar, turn example.com into http://example.com.
+pub fn parse_host(request: &str) -> Option<ServoUrl> {
+    // See: https://github.com/paulrouget/servoshell/issues/59
+    if request.ends_with(".com") || request.ends_with(".org") || request.ends_with(".net") {
+        ServoUrl::parse(&format!("http://{}", request)).ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::File;
+    use super::*;
+
+    #[test]
+    fn pairs() {
+        assert_eq!(parse_pair::<u32>("800x600", 'x'), Some((800, 600)));
+        assert_eq!(parse_pair::<i32>(" 10 , -20 ", ','), Some((10, -20)));
+        assert_eq!(parse_pair::<u32>("800x", 'x'), None);
+        assert_eq!(parse_pair::<u32>("axb", 'x'), None);
+        assert_eq!(parse_pair::<u32>("800", 'x'), None);
+        assert_eq!(parse_pair::<u32>("800,600", 'x'), None);
+        assert_eq!(parse_pair::<u32>("-800x600", 'x'), None);
+    }
+
+    #[test]
+    fn full_urls() {
+        assert_eq!(parse_url("https://servo.org/"), Some("https://servo.org/".to_owned()));
+        assert_eq!(parse_url("about:blank"), Some("about:blank".to_owned()));
+    }
+
+    #[test]
+    fn host_names() {
+        assert_eq!(parse_url("servo.org"), Some("http://servo.org/".to_owned()));
+        assert_eq!(parse_host("example.com").map(|url| url.into_string()),
+                   Some("http://example.com/".to_owned()));
+        assert!(parse_host("example.fr").is_none());
+        assert!(parse_url("servo").is_none());
+    }
+
+    #[test]
+    fn file_paths() {
+        let path = env::temp_dir().join("servoshell-cli-test.html");
+        File::create(&path).unwrap();
+        let url = parse_url(path.to_str().unwrap()).unwrap();
+        assert!(url.starts_with("file:///"));
+        assert!(url.ends_with("/servoshell-cli-test.html"));
+        fs::remove_file(&path).unwrap();
+        assert!(parse_url(path.to_str().unwrap()).is_none());
+    }
+}
@@ -0,0 +1,141 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Save resources to disk. Servo doesn't handle downloads, so files are
+//! fetched again by ServoShell, outside of the page.
+//!
+//! The downloads of the session are listed in `servoshell://downloads`.
+
+use hyper::Client;
+use hyper::net::HttpsConnector;
+use hyper_openssl::OpensslClient;
+use servo::ServoUrl;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DownloadStatus {
+    InProgress,
+    Done,
+    Failed(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Download {
+    pub url: ServoUrl,
+    pub path: PathBuf,
+    pub status: DownloadStatus,
+}
+
+pub struct Downloads(Arc<Mutex<Vec<Download>>>);
+
+impl Downloads {
+    pub fn new() -> Downloads {
+        Downloads(Arc::new(Mutex::new(vec![])))
+    }
+
+    /// Fetch `url` into the downloads directory, in the background.
+    pub fn save(&self, url: ServoUrl) {
+        let dir = match downloads_dir() {
+            Some(dir) => dir,
+            None => {
+                warn!("Can't find the downloads directory");
+                return;
+            }
+        };
+        let path = unique_path(&dir, &file_name(&url));
+        let index = {
+            let mut downloads = self.0.lock().unwrap();
+            downloads.push(Download {
+                               url: url.clone(),
+                               path: path.clone(),
+                               status: DownloadStatus::InProgress,
+                           });
+            downloads.len() - 1
+        };
+        let downloads = self.0.clone();
+        thread::spawn(move || {
+            let status = match fetch(&url, &path) {
+                Ok(()) => {
+                    info!("Saved {} to {}", url, path.display());
+                    DownloadStatus::Done
+                }
+                Err(e) => {
+                    warn!("Can't save {}: {}", url, e);
+                    DownloadStatus::Failed(e)
+                }
+            };
+            downloads.lock().unwrap()[index].status = status;
+        });
+    }
+
+    /// Oldest first.
+    pub fn list(&self) -> Vec<Download> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+fn fetch(url: &ServoUrl, path: &Path) -> Result<(), String> {
+    let mut source: Box<Read> = match url.scheme() {
+        "file" => {
+            let source = url.as_url()
+                .to_file_path()
+                .map_err(|_| "Invalid file URL".to_owned())?;
+            box File::open(source).map_err(|e| e.to_string())?
+        }
+        "http" | "https" => {
+            let ssl = OpensslClient::new().map_err(|e| e.to_string())?;
+            let client = Client::with_connector(HttpsConnector::new(ssl));
+            let response = client
+                .get(url.as_str())
+                .send()
+                .map_err(|e| e.to_string())?;
+            if !response.status.is_success() {
+                return Err(format!("Server returned {}", response.status));
+            }
+            box response
+        }
+        scheme => return Err(format!("Unsupported scheme: {}", scheme)),
+    };
+    let mut file = File::create(path).map_err(|e| e.to_string())?;
+    if let Err(e) = io::copy(&mut source, &mut file) {
+        fs::remove_file(path).ok();
+        return Err(e.to_string());
+    }
+    Ok(())
+}
+
+fn downloads_dir() -> Option<PathBuf> {
+    env::home_dir().map(|home| {
+                            let downloads = home.join("Downloads");
+                            if downloads.is_dir() { downloads } else { home }
+                        })
+}
+
+fn file_name(url: &ServoUrl) -> String {
+    match url.as_url().path_segments().and_then(|segments| segments.last()) {
+        Some(name) if !name.is_empty() => name.to_owned(),
+        _ => "download".to_owned(),
+    }
+}
+
+// "image.png", then "image (1).png", "image (2).png"…
+fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let mut path = dir.join(name);
+    let mut count = 1;
+    while path.exists() {
+        let name = Path::new(name);
+        let stem = name.file_stem().and_then(|s| s.to_str()).unwrap_or("download");
+        path = match name.extension().and_then(|e| e.to_str()) {
+            Some(ext) => dir.join(format!("{} ({}).{}", stem, count, ext)),
+            None => dir.join(format!("{} ({})", stem, count)),
+        };
+        count += 1;
+    }
+    path
+}
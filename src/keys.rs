@@ -0,0 +1,489 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Key bindings.
+//!
+//! The default bindings can be extended or overridden by a `keys.json`
+//! file in the profile directory, mapping key sequences to commands:
+//!
+//! ```json
+//! {
+//!   "CmdOrCtrl+R": "Reload",
+//!   "Ctrl+K Ctrl+T": "NewTab",
+//!   "Alt+1": {"SelectTab": 0},
+//!   "CmdOrCtrl+W": null
+//! }
+//! ```
+//!
+//! Chords in a sequence are separated by spaces. Key names are the ones of
+//! servo's `Key` enum. A `null` command removes a default binding.
+//!
+//! Key bindings only match the keys that the page didn't prevent.
+
+use serde_json::{self, Value};
+use servo::{Key, KeyModifiers, KeyState};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use traits::app::AppCommand;
+use traits::window::{LinkHintAction, WindowCommand};
+
+const KEYS_FILE: &'static str = "keys.json";
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum KeyCommand {
+    Window(WindowCommand),
+    App(AppCommand),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum KeyMatch {
+    Command(KeyCommand),
+    // Part of a sequence, or already handled
+    Consumed,
+    Unhandled,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Chord {
+    key: Key,
+    modifiers: KeyModifiers,
+}
+
+fn default_bindings() -> Vec<(&'static str, KeyCommand)> {
+    use self::KeyCommand::Window as W;
+    vec![("CmdOrCtrl+R", W(WindowCommand::Reload)),
+         ("CmdOrCtrl+Left", W(WindowCommand::NavigateBack)),
+         ("CmdOrCtrl+Right", W(WindowCommand::NavigateForward)),
+         ("Backspace", W(WindowCommand::NavigateBack)),
+         ("Shift+Backspace", W(WindowCommand::NavigateForward)),
+         ("CmdOrCtrl+L", W(WindowCommand::OpenLocation)),
+         ("CmdOrCtrl+D", W(WindowCommand::ToggleBookmark)),
+         ("CmdOrCtrl+=", W(WindowCommand::ZoomIn)),
+         ("CmdOrCtrl+-", W(WindowCommand::ZoomOut)),
+         ("CmdOrCtrl+0", W(WindowCommand::ZoomToActualSize)),
+         ("CmdOrCtrl+Alt+0", W(WindowCommand::ResetPinchZoom)),
+         ("F11", W(WindowCommand::ToggleFullscreen)),
+         ("CmdOrCtrl+T", W(WindowCommand::NewTab)),
+         ("CmdOrCtrl+W", W(WindowCommand::CloseTab)),
+         ("Ctrl+Tab", W(WindowCommand::NextTab)),
+         ("Ctrl+Shift+Tab", W(WindowCommand::PrevTab)),
+         ("CmdOrCtrl+1", W(WindowCommand::SelectTab(0))),
+         ("CmdOrCtrl+2", W(WindowCommand::SelectTab(1))),
+         ("CmdOrCtrl+3", W(WindowCommand::SelectTab(2))),
+         ("CmdOrCtrl+4", W(WindowCommand::SelectTab(3))),
+         ("CmdOrCtrl+5", W(WindowCommand::SelectTab(4))),
+         ("CmdOrCtrl+6", W(WindowCommand::SelectTab(5))),
+         ("CmdOrCtrl+7", W(WindowCommand::SelectTab(6))),
+         ("CmdOrCtrl+8", W(WindowCommand::SelectTab(7))),
+         ("CmdOrCtrl+9", W(WindowCommand::SelectTab(8))),
+         ("CmdOrCtrl+Alt+F", W(WindowCommand::ToggleOptionFragmentBorders)),
+         ("CmdOrCtrl+Alt+D", W(WindowCommand::ToggleOptionParallelDisplayListBuidling)),
+         ("CmdOrCtrl+Alt+P", W(WindowCommand::ToggleOptionShowParallelLayout)),
+         ("CmdOrCtrl+Alt+M", W(WindowCommand::ToggleOptionConvertMouseToTouch)),
+         ("CmdOrCtrl+Alt+B", W(WindowCommand::ToggleOptionTileBorders)),
+         ("CmdOrCtrl+Alt+L", W(WindowCommand::ToggleOptionShowLogs)),
+         ("CmdOrCtrl+Alt+S", W(WindowCommand::ExportLogs)),
+         ("CmdOrCtrl+Alt+H", W(WindowCommand::ShowLinkHints(LinkHintAction::Click))),
+         ("CmdOrCtrl+Alt+N", W(WindowCommand::ShowLinkHints(LinkHintAction::OpenInNewTab))),
+         ("CmdOrCtrl+Alt+Y", W(WindowCommand::ShowLinkHints(LinkHintAction::CopyUrl)))]
+}
+
+pub struct Keymap {
+    bindings: Vec<(Vec<Chord>, KeyCommand)>,
+    pending: Vec<Chord>,
+    // glutin reports printable keys twice, the second time with the
+    // character. Used to swallow the second event.
+    last_consumed: Option<Chord>,
+}
+
+impl Keymap {
+    pub fn load(profile: Option<&Path>) -> Keymap {
+        let bindings = default_bindings()
+            .into_iter()
+            .map(|(keys, command)| {
+                     (parse_sequence(keys).expect("Invalid default key binding"), command)
+                 })
+            .collect();
+        let mut keymap = Keymap {
+            bindings,
+            pending: vec![],
+            last_consumed: None,
+        };
+        if let Some(path) = profile.map(|p| p.join(KEYS_FILE)) {
+            if path.exists() {
+                match read(&path) {
+                    Ok(user) => keymap.merge(user),
+                    Err(e) => warn!("Can't read {}: {}", path.display(), e),
+                }
+            }
+        }
+        keymap
+    }
+
+    /// Add the user bindings. Conflicting user bindings are ignored.
+    /// User bindings replace the default bindings they conflict with.
+    fn merge(&mut self, user: BTreeMap<String, Value>) {
+        let mut bindings: Vec<(Vec<Chord>, Option<KeyCommand>)> = vec![];
+        for (keys, command) in user {
+            let sequence = match parse_sequence(&keys) {
+                Ok(sequence) => sequence,
+                Err(e) => {
+                    warn!("Invalid key binding \"{}\": {}", keys, e);
+                    continue;
+                }
+            };
+            let command = match serde_json::from_value(command) {
+                Ok(command) => command,
+                Err(_) => {
+                    warn!("Invalid command for key binding \"{}\"", keys);
+                    continue;
+                }
+            };
+            if let Some(&(ref other, _)) =
+                bindings
+                    .iter()
+                    .find(|&&(ref other, _)| conflicts(other, &sequence)) {
+                warn!("Key binding \"{}\" conflicts with \"{}\"",
+                      keys,
+                      format_sequence(other));
+                continue;
+            }
+            bindings.push((sequence, command));
+        }
+        self.bindings
+            .retain(|&(ref sequence, _)| {
+                        !bindings
+                             .iter()
+                             .any(|&(ref other, _)| conflicts(sequence, other))
+                    });
+        let added = bindings
+            .into_iter()
+            .filter_map(|(sequence, command)| command.map(|command| (sequence, command)));
+        self.bindings.extend(added);
+    }
+
+    /// Active bindings, as displayed to the user.
+    pub fn get_bindings(&self) -> Vec<(String, &KeyCommand)> {
+        self.bindings
+            .iter()
+            .map(|&(ref sequence, ref command)| (format_sequence(sequence), command))
+            .collect()
+    }
+
+    pub fn handle_key(&mut self,
+                      c: Option<char>,
+                      key: Key,
+                      state: KeyState,
+                      modifiers: KeyModifiers)
+                      -> KeyMatch {
+        if state != KeyState::Pressed || is_modifier(key) {
+            return KeyMatch::Unhandled;
+        }
+        let chord = Chord { key, modifiers };
+        let last_consumed = self.last_consumed.take();
+        if c.is_some() && last_consumed == Some(chord) {
+            return KeyMatch::Consumed;
+        }
+
+        self.pending.push(chord);
+        let found = self.bindings
+            .iter()
+            .find(|&&(ref sequence, _)| *sequence == self.pending)
+            .map(|&(_, ref command)| command.clone());
+        let result = if let Some(command) = found {
+            self.pending.clear();
+            KeyMatch::Command(command)
+        } else if self.bindings
+                      .iter()
+                      .any(|&(ref sequence, _)| sequence.starts_with(&self.pending)) {
+            KeyMatch::Consumed
+        } else if self.pending.len() > 1 {
+            // Sequence aborted. Try again with this key only.
+            self.pending.clear();
+            return self.handle_key(c, key, state, modifiers);
+        } else {
+            self.pending.clear();
+            return KeyMatch::Unhandled;
+        };
+        if c.is_none() {
+            self.last_consumed = Some(chord);
+        }
+        result
+    }
+}
+
+fn conflicts(a: &[Chord], b: &[Chord]) -> bool {
+    a.starts_with(b) || b.starts_with(a)
+}
+
+fn is_modifier(key: Key) -> bool {
+    match key {
+        Key::LeftShift | Key::LeftControl | Key::LeftAlt | Key::LeftSuper | Key::RightShift |
+        Key::RightControl | Key::RightAlt | Key::RightSuper => true,
+        _ => false,
+    }
+}
+
+fn cmd_or_ctrl() -> KeyModifiers {
+    if cfg!(target_os = "macos") {
+        KeyModifiers::SUPER
+    } else {
+        KeyModifiers::CONTROL
+    }
+}
+
+fn parse_sequence(keys: &str) -> Result<Vec<Chord>, String> {
+    let sequence = keys.split_whitespace()
+        .map(parse_chord)
+        .collect::<Result<Vec<Chord>, String>>()?;
+    if sequence.is_empty() {
+        return Err("Empty key binding".to_owned());
+    }
+    Ok(sequence)
+}
+
+fn parse_chord(chord: &str) -> Result<Chord, String> {
+    let mut parts: Vec<&str> = chord.split('+').collect();
+    let key = parts.pop().unwrap();
+    let mut modifiers = KeyModifiers::empty();
+    for part in parts {
+        let modifier = match part.to_lowercase().as_str() {
+            "ctrl" | "control" => KeyModifiers::CONTROL,
+            "shift" => KeyModifiers::SHIFT,
+            "alt" => KeyModifiers::ALT,
+            "cmd" | "super" => KeyModifiers::SUPER,
+            "cmdorctrl" => cmd_or_ctrl(),
+            _ => return Err(format!("Unknown modifier: {}", part)),
+        };
+        modifiers.insert(modifier);
+    }
+    let key = parse_key(key).ok_or_else(|| format!("Unknown key: {}", key))?;
+    Ok(Chord { key, modifiers })
+}
+
+// Accept servo key names, plus single characters for the common keys
+fn parse_key(name: &str) -> Option<Key> {
+    let name = match name {
+        "=" => "Equal".to_owned(),
+        "-" => "Minus".to_owned(),
+        "," => "Comma".to_owned(),
+        "." => "Period".to_owned(),
+        "/" => "Slash".to_owned(),
+        ";" => "Semicolon".to_owned(),
+        "[" => "LeftBracket".to_owned(),
+        "]" => "RightBracket".to_owned(),
+        name if name.len() == 1 && name.chars().all(|c| c.is_digit(10)) => {
+            format!("Num{}", name)
+        }
+        name if name.len() == 1 => name.to_uppercase(),
+        name => name.to_owned(),
+    };
+    serde_json::from_value(Value::String(name)).ok()
+}
+
+fn format_sequence(sequence: &[Chord]) -> String {
+    sequence
+        .iter()
+        .map(format_chord)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn format_chord(chord: &Chord) -> String {
+    let mut text = String::new();
+    if chord.modifiers.contains(KeyModifiers::CONTROL) {
+        text.push_str("Ctrl+");
+    }
+    if chord.modifiers.contains(KeyModifiers::ALT) {
+        text.push_str("Alt+");
+    }
+    if chord.modifiers.contains(KeyModifiers::SHIFT) {
+        text.push_str("Shift+");
+    }
+    if chord.modifiers.contains(KeyModifiers::SUPER) {
+        text.push_str(if cfg!(target_os = "macos") {
+                          "Cmd+"
+                      } else {
+                          "Super+"
+                      });
+    }
+    text.push_str(&format!("{:?}", chord.key));
+    text
+}
+
+fn read(path: &Path) -> Result<BTreeMap<String, Value>, String> {
+    let mut content = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut content))
+        .map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use servo::{Key, KeyModifiers, KeyState};
+    use super::*;
+    use traits::window::WindowCommand;
+
+    fn keymap(bindings: &[(&str, WindowCommand)]) -> Keymap {
+        Keymap {
+            bindings: bindings
+                .iter()
+                .map(|&(keys, ref command)| {
+                         (parse_sequence(keys).unwrap(), KeyCommand::Window(command.clone()))
+                     })
+                .collect(),
+            pending: vec![],
+            last_consumed: None,
+        }
+    }
+
+    fn command_for(keymap: &Keymap, keys: &str) -> Option<KeyCommand> {
+        let sequence = parse_sequence(keys).unwrap();
+        keymap
+            .bindings
+            .iter()
+            .find(|&&(ref other, _)| *other == sequence)
+            .map(|&(_, ref command)| command.clone())
+    }
+
+    fn press(keymap: &mut Keymap, key: Key, modifiers: KeyModifiers) -> KeyMatch {
+        keymap.handle_key(None, key, KeyState::Pressed, modifiers)
+    }
+
+    fn window_command(command: WindowCommand) -> KeyMatch {
+        KeyMatch::Command(KeyCommand::Window(command))
+    }
+
+    #[test]
+    fn parse_chords() {
+        assert_eq!(parse_chord("Ctrl+Shift+K"),
+                   Ok(Chord {
+                          key: Key::K,
+                          modifiers: KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+                      }));
+        assert_eq!(parse_chord("alt+="),
+                   Ok(Chord {
+                          key: Key::Equal,
+                          modifiers: KeyModifiers::ALT,
+                      }));
+        assert_eq!(parse_chord("1"),
+                   Ok(Chord {
+                          key: Key::Num1,
+                          modifiers: KeyModifiers::empty(),
+                      }));
+        assert_eq!(parse_chord("CmdOrCtrl+W").map(|chord| chord.modifiers),
+                   Ok(cmd_or_ctrl()));
+        assert!(parse_chord("Hyper+K").is_err());
+        assert!(parse_chord("Ctrl+Nope").is_err());
+    }
+
+    #[test]
+    fn parse_sequences() {
+        let sequence = parse_sequence("Ctrl+K  Ctrl+T").unwrap();
+        assert_eq!(sequence,
+                   vec![parse_chord("Ctrl+K").unwrap(), parse_chord("Ctrl+T").unwrap()]);
+        assert!(parse_sequence("").is_err());
+        assert!(parse_sequence(" ").is_err());
+        assert!(parse_sequence("Ctrl+K Nope").is_err());
+    }
+
+    #[test]
+    fn conflicting_sequences() {
+        let ctrl_k = parse_sequence("Ctrl+K").unwrap();
+        let ctrl_k_t = parse_sequence("Ctrl+K Ctrl+T").unwrap();
+        let ctrl_t = parse_sequence("Ctrl+T").unwrap();
+        assert!(conflicts(&ctrl_k, &ctrl_k));
+        assert!(conflicts(&ctrl_k, &ctrl_k_t));
+        assert!(conflicts(&ctrl_k_t, &ctrl_k));
+        assert!(!conflicts(&ctrl_t, &ctrl_k_t));
+    }
+
+    #[test]
+    fn merge_user_bindings() {
+        let mut keymap = keymap(&[("Ctrl+R", WindowCommand::Reload),
+                                  ("Ctrl+W", WindowCommand::CloseTab),
+                                  ("Ctrl+K", WindowCommand::OpenLocation)]);
+        let user = serde_json::from_str(r#"{
+            "Ctrl+R": "NewTab",
+            "Ctrl+W": null,
+            "Ctrl+K Ctrl+T": "NextTab",
+            "Ctrl+K Ctrl+T Ctrl+T": "PrevTab",
+            "Alt+1": {"SelectTab": 0},
+            "Alt+2": "NoSuchCommand"
+        }"#)
+                .unwrap();
+        keymap.merge(user);
+        // User bindings replace the defaults
+        assert_eq!(command_for(&keymap, "Ctrl+R"),
+                   Some(KeyCommand::Window(WindowCommand::NewTab)));
+        // null removes a binding
+        assert_eq!(command_for(&keymap, "Ctrl+W"), None);
+        // A sequence replaces the default bound to its first chord
+        assert_eq!(command_for(&keymap, "Ctrl+K"), None);
+        assert_eq!(command_for(&keymap, "Ctrl+K Ctrl+T"),
+                   Some(KeyCommand::Window(WindowCommand::NextTab)));
+        // Conflicts with an earlier user binding
+        assert_eq!(command_for(&keymap, "Ctrl+K Ctrl+T Ctrl+T"), None);
+        assert_eq!(command_for(&keymap, "Alt+1"),
+                   Some(KeyCommand::Window(WindowCommand::SelectTab(0))));
+        assert_eq!(command_for(&keymap, "Alt+2"), None);
+        assert_eq!(keymap.bindings.len(), 3);
+    }
+
+    #[test]
+    fn default_bindings_parse() {
+        let keymap = Keymap::load(None);
+        assert_eq!(keymap.bindings.len(), default_bindings().len());
+    }
+
+    #[test]
+    fn sequence_completed() {
+        let mut keymap = keymap(&[("Ctrl+K Ctrl+T", WindowCommand::NewTab)]);
+        assert_eq!(press(&mut keymap, Key::K, KeyModifiers::CONTROL), KeyMatch::Consumed);
+        assert_eq!(keymap.pending.len(), 1);
+        assert_eq!(press(&mut keymap, Key::T, KeyModifiers::CONTROL),
+                   window_command(WindowCommand::NewTab));
+        assert!(keymap.pending.is_empty());
+    }
+
+    #[test]
+    fn sequence_aborted() {
+        let mut keymap = keymap(&[("Ctrl+K Ctrl+T", WindowCommand::NewTab),
+                                  ("Ctrl+R", WindowCommand::Reload)]);
+        // The aborting key is tried on its own
+        assert_eq!(press(&mut keymap, Key::K, KeyModifiers::CONTROL), KeyMatch::Consumed);
+        assert_eq!(press(&mut keymap, Key::R, KeyModifiers::CONTROL),
+                   window_command(WindowCommand::Reload));
+        assert!(keymap.pending.is_empty());
+        // And goes to the page if it's not bound
+        assert_eq!(press(&mut keymap, Key::K, KeyModifiers::CONTROL), KeyMatch::Consumed);
+        assert_eq!(press(&mut keymap, Key::X, KeyModifiers::CONTROL), KeyMatch::Unhandled);
+        assert!(keymap.pending.is_empty());
+        // Modifiers alone don't abort a sequence
+        assert_eq!(press(&mut keymap, Key::K, KeyModifiers::CONTROL), KeyMatch::Consumed);
+        assert_eq!(press(&mut keymap, Key::LeftControl, KeyModifiers::CONTROL),
+                   KeyMatch::Unhandled);
+        assert_eq!(press(&mut keymap, Key::T, KeyModifiers::CONTROL),
+                   window_command(WindowCommand::NewTab));
+    }
+
+    #[test]
+    fn repeated_glutin_event() {
+        let mut keymap = keymap(&[("Ctrl+R", WindowCommand::Reload)]);
+        assert_eq!(press(&mut keymap, Key::R, KeyModifiers::CONTROL),
+                   window_command(WindowCommand::Reload));
+        // The same key again, with its character, is swallowed once
+        assert_eq!(keymap.handle_key(Some('r'), Key::R, KeyState::Pressed, KeyModifiers::CONTROL),
+                   KeyMatch::Consumed);
+        assert_eq!(keymap.handle_key(Some('r'), Key::R, KeyState::Pressed, KeyModifiers::CONTROL),
+                   window_command(WindowCommand::Reload));
+        assert_eq!(keymap.handle_key(None, Key::R, KeyState::Released, KeyModifiers::CONTROL),
+                   KeyMatch::Unhandled);
+    }
+}
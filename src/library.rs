@@ -0,0 +1,264 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Browsing history and bookmarks, backed by JSON files in the profile
+//! directory. In private mode, they are only kept in memory.
+//!
+//! Files are written from a background thread, a few seconds after a
+//! change, so a burst of navigations is saved once.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const HISTORY_FILE: &'static str = "history.json";
+const BOOKMARKS_FILE: &'static str = "bookmarks.json";
+const MAX_HISTORY_ENTRIES: usize = 1000;
+const SAVE_DELAY_MS: u64 = 2000;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Entry {
+    pub url: String,
+    pub title: Option<String>,
+    // Seconds since the Unix epoch
+    pub time: u64,
+}
+
+impl Entry {
+    fn new(url: &str, title: Option<String>) -> Entry {
+        Entry {
+            url: url.to_owned(),
+            title,
+            time: now(),
+        }
+    }
+}
+
+pub struct Library {
+    profile: Option<PathBuf>,
+    // Oldest first
+    history: Vec<Entry>,
+    bookmarks: Vec<Entry>,
+    // None in private mode
+    history_saver: Option<Saver>,
+    bookmarks_saver: Option<Saver>,
+}
+
+impl Library {
+    pub fn load(profile: Option<&Path>) -> Library {
+        Library {
+            profile: profile.map(|p| p.to_owned()),
+            history: profile.map_or(vec![], |p| read_or_warn(&p.join(HISTORY_FILE))),
+            bookmarks: profile.map_or(vec![], |p| read_or_warn(&p.join(BOOKMARKS_FILE))),
+            history_saver: profile.map(|p| Saver::new(p.join(HISTORY_FILE))),
+            bookmarks_saver: profile.map(|p| Saver::new(p.join(BOOKMARKS_FILE))),
+        }
+    }
+
+    /// None in private mode.
+    pub fn profile(&self) -> Option<&Path> {
+        self.profile.as_ref().map(|p| p.as_path())
+    }
+
+    pub fn history(&self) -> &[Entry] {
+        &self.history
+    }
+
+    pub fn bookmarks(&self) -> &[Entry] {
+        &self.bookmarks
+    }
+
+    pub fn add_visit(&mut self, url: &str) {
+        let revisit = self.history.last().map_or(false, |last| last.url == url);
+        if revisit {
+            // Reloads and fragment changes
+            self.history.last_mut().unwrap().time = now();
+        } else {
+            self.history.push(Entry::new(url, None));
+            if self.history.len() > MAX_HISTORY_ENTRIES {
+                self.history.remove(0);
+            }
+        }
+        self.save_history();
+    }
+
+    /// Titles usually arrive after the visit.
+    pub fn set_title(&mut self, url: &str, title: Option<String>) {
+        if let Some(entry) = self.history.iter_mut().rev().find(|e| e.url == url) {
+            entry.title = title;
+        } else {
+            return;
+        }
+        self.save_history();
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+        self.save_history();
+    }
+
+    pub fn is_bookmarked(&self, url: &str) -> bool {
+        self.bookmarks.iter().any(|e| e.url == url)
+    }
+
+    pub fn add_bookmark(&mut self, url: &str, title: Option<String>) {
+        if !self.is_bookmarked(url) {
+            self.bookmarks.push(Entry::new(url, title));
+            self.save_bookmarks();
+        }
+    }
+
+    pub fn remove_bookmark(&mut self, url: &str) {
+        self.bookmarks.retain(|e| e.url != url);
+        self.save_bookmarks();
+    }
+
+    /// Write the pending changes now. Exiting the process doesn't drop
+    /// the library.
+    pub fn flush(&self) {
+        for saver in self.history_saver.iter().chain(self.bookmarks_saver.iter()) {
+            saver.flush();
+        }
+    }
+
+    fn save_history(&self) {
+        if let Some(ref saver) = self.history_saver {
+            saver.save(&self.history);
+        }
+    }
+
+    fn save_bookmarks(&self) {
+        if let Some(ref saver) = self.bookmarks_saver {
+            saver.save(&self.bookmarks);
+        }
+    }
+}
+
+enum Message {
+    Save(Vec<Entry>),
+    // Write the pending entries now, then reply
+    Flush(Sender<()>),
+}
+
+// Writes the last entries it was given, SAVE_DELAY_MS after the first
+// change. Pending changes are written when it's flushed or dropped.
+struct Saver {
+    sender: Option<Sender<Message>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Saver {
+    fn new(path: PathBuf) -> Saver {
+        let (sender, receiver) = channel::<Message>();
+        let thread = thread::spawn(move || {
+            while let Ok(message) = receiver.recv() {
+                let mut entries = match message {
+                    Message::Save(entries) => entries,
+                    Message::Flush(done) => {
+                        done.send(()).ok();
+                        continue;
+                    }
+                };
+                let deadline = Instant::now() + Duration::from_millis(SAVE_DELAY_MS);
+                let mut flushed = None;
+                loop {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    match receiver.recv_timeout(deadline - now) {
+                        Ok(Message::Save(newer)) => entries = newer,
+                        Ok(Message::Flush(done)) => {
+                            flushed = Some(done);
+                            break;
+                        }
+                        // Timeout, or dropped: write now
+                        Err(_) => break,
+                    }
+                }
+                write_or_warn(&path, &entries);
+                if let Some(done) = flushed {
+                    done.send(()).ok();
+                }
+            }
+        });
+        Saver {
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    fn save(&self, entries: &[Entry]) {
+        if let Some(ref sender) = self.sender {
+            sender.send(Message::Save(entries.to_vec())).ok();
+        }
+    }
+
+    // Blocks until the pending entries are written
+    fn flush(&self) {
+        if let Some(ref sender) = self.sender {
+            let (done, written) = channel();
+            if sender.send(Message::Flush(done)).is_ok() {
+                written.recv().ok();
+            }
+        }
+    }
+}
+
+impl Drop for Saver {
+    fn drop(&mut self) {
+        // Disconnects the channel, the thread writes what's pending and exits
+        self.sender.take();
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn read_or_warn<T: DeserializeOwned + Default>(path: &Path) -> T {
+    if !path.exists() {
+        return T::default();
+    }
+    read(path).unwrap_or_else(|e| {
+                                  warn!("Can't read {}: {}", path.display(), e);
+                                  T::default()
+                              })
+}
+
+fn read<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let mut content = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut content))
+        .map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+fn write_or_warn<T: Serialize>(path: &Path, value: &T) {
+    if let Err(e) = write(path, value) {
+        warn!("Can't write {}: {}", path.display(), e);
+    }
+}
+
+// Through a temporary file, so a crash can't leave a truncated file
+fn write<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let content = serde_json::to_string(value).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    File::create(&tmp)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .and_then(|_| fs::rename(&tmp, path))
+        .map_err(|e| e.to_string())
+}
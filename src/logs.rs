@@ -2,33 +2,220 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+//! Logs are kept in memory, in a ring buffer, to be displayed by the
+//! window. When the buffer is full, the oldest logs are dropped.
+//!
+//! The maximum level can be changed at runtime, and overridden for some
+//! targets: with the level set to warn and `script` set to debug, only the
+//! warnings and the errors are kept, except for `script` and `script::*`.
+//!
+//! Logs can also be written to a file in the profile directory, one JSON
+//! object per line: `{"timestamp":1510000000.5,"level":"INFO",
+//! "target":"servoshell","message":"starting"}`. The file is rotated when
+//! it gets too big. Writes are buffered, the buffer is flushed every second
+//! and after warnings and errors.
+
 use log::*;
+use serde_json;
+use std::cmp;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_CAPACITY: usize = 1000;
+
+const LOG_FILE: &'static str = "servoshell.log";
+const MAX_LOG_FILE_SIZE: u64 = 1024 * 1024;
+// servoshell.log.1 to servoshell.log.3
+const ROTATED_LOG_FILES: usize = 3;
+const LOG_FILE_FLUSH_MS: u64 = 1000;
 
+#[derive(Clone, Debug)]
 pub struct ShellLog {
+    pub timestamp: SystemTime,
     pub level: LogLevel,
     pub target: String,
     pub message: String,
 }
 
-pub struct ShellLogs(Mutex<Vec<ShellLog>>);
+struct LogBuffer {
+    logs: VecDeque<ShellLog>,
+    capacity: usize,
+    level: LogLevelFilter,
+    // Per target levels, override `level`
+    targets: Vec<(String, LogLevelFilter)>,
+    // Logs ever recorded
+    count: usize,
+    // Logs already returned by pull
+    pulled: usize,
+    file: Option<LogFile>,
+}
+
+impl LogBuffer {
+    fn new(level: LogLevelFilter,
+           targets: &[(String, LogLevelFilter)],
+           capacity: usize)
+           -> LogBuffer {
+        LogBuffer {
+            logs: VecDeque::new(),
+            capacity,
+            level,
+            targets: targets.to_vec(),
+            count: 0,
+            pulled: 0,
+            file: None,
+        }
+    }
+
+    fn level_for(&self, target: &str) -> LogLevelFilter {
+        // The most specific target wins
+        self.targets
+            .iter()
+            .filter(|&&(ref prefix, _)| {
+                        target == prefix.as_str() || target.starts_with(&format!("{}::", prefix))
+                    })
+            .max_by_key(|&&(ref prefix, _)| prefix.len())
+            .map_or(self.level, |&(_, level)| level)
+    }
+
+    // The highest level of all the targets
+    fn max_level(&self) -> LogLevelFilter {
+        self.targets
+            .iter()
+            .fold(self.level, |max, &(_, level)| cmp::max(max, level))
+    }
+
+    fn push(&mut self, log: ShellLog) {
+        let failed = match self.file {
+            Some(ref mut file) => file.write(&log).is_err(),
+            None => false,
+        };
+        if failed {
+            // Can't log from the logger
+            eprintln!("Can't write to the log file, disabling it");
+            self.file = None;
+        }
+        if self.capacity > 0 {
+            while self.logs.len() >= self.capacity {
+                self.logs.pop_front();
+            }
+            self.logs.push_back(log);
+        }
+        self.count += 1;
+    }
+
+    fn pull(&mut self) -> Vec<ShellLog> {
+        let new = cmp::min(self.count - self.pulled, self.logs.len());
+        self.pulled = self.count;
+        let skip = self.logs.len() - new;
+        self.logs.iter().skip(skip).cloned().collect()
+    }
+
+    fn dropped(&self) -> usize {
+        self.count - self.logs.len()
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.logs.len() > capacity {
+            self.logs.pop_front();
+        }
+    }
+}
+
+pub struct ShellLogs {
+    buffer: Mutex<LogBuffer>,
+    // The log crate skips the records above this level before calling us
+    max_log_level: MaxLogLevelFilter,
+}
 
 impl ShellLogs {
+    /// The logs recorded since the last call, if they were not dropped.
     pub fn get_logs(&self) -> Vec<ShellLog> {
-        let mut vec = self.0.lock().unwrap();
-        let res = vec.drain(..).collect();
-        res
+        self.buffer.lock().unwrap().pull()
+    }
+
+    /// All the logs in the buffer.
+    pub fn all_logs(&self) -> Vec<ShellLog> {
+        self.buffer.lock().unwrap().logs.iter().cloned().collect()
+    }
+
+    /// Number of logs that didn't fit in the buffer.
+    pub fn dropped(&self) -> usize {
+        self.buffer.lock().unwrap().dropped()
+    }
+
+    /// Maximum number of logs kept in the buffer.
+    pub fn capacity(&self) -> usize {
+        self.buffer.lock().unwrap().capacity
+    }
+
+    pub fn set_capacity(&self, capacity: usize) {
+        self.buffer.lock().unwrap().set_capacity(capacity);
+    }
+
+    /// The maximum level, and the per target levels overriding it.
+    pub fn levels(&self) -> (LogLevelFilter, Vec<(String, LogLevelFilter)>) {
+        let buffer = self.buffer.lock().unwrap();
+        (buffer.level, buffer.targets.clone())
+    }
+
+    pub fn set_level(&self, level: LogLevelFilter) {
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.level = level;
+        self.max_log_level.set(buffer.max_level());
+    }
+
+    /// Write the buffered logs to the log file, if any.
+    pub fn flush(&self) {
+        let mut buffer = self.buffer.lock().unwrap();
+        let failed = match buffer.file {
+            Some(ref mut file) => file.flush().is_err(),
+            None => false,
+        };
+        if failed {
+            eprintln!("Can't write to the log file, disabling it");
+            buffer.file = None;
+        }
+    }
+
+    /// Also write the logs to a file in `dir`.
+    pub fn set_file(&self, dir: &Path) -> io::Result<()> {
+        let file = LogFile::open(dir.join(LOG_FILE))?;
+        self.buffer.lock().unwrap().file = Some(file);
+        Ok(())
+    }
+
+    /// Change the level of a target and its children. `None` removes the
+    /// override.
+    pub fn set_target_level(&self, target: &str, level: Option<LogLevelFilter>) {
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.targets.retain(|&(ref other, _)| other != target);
+        if let Some(level) = level {
+            buffer.targets.push((target.to_owned(), level));
+        }
+        self.max_log_level.set(buffer.max_level());
     }
 }
 
 pub struct Logger(Arc<ShellLogs>);
 
 impl Logger {
-    pub fn init() -> Arc<ShellLogs> {
+    pub fn init(level: LogLevelFilter,
+                targets: &[(String, LogLevelFilter)],
+                capacity: usize)
+                -> Arc<ShellLogs> {
         let mut rv = None;
         set_logger(|max_log_level| {
-                       max_log_level.set(LogLevelFilter::Info);
-                       let logs = Arc::new(ShellLogs(Mutex::new(Vec::new())));
+                       let buffer = LogBuffer::new(level, targets, capacity);
+                       max_log_level.set(buffer.max_level());
+                       let logs = Arc::new(ShellLogs {
+                                               buffer: Mutex::new(buffer),
+                                               max_log_level,
+                                           });
                        rv = Some(logs.clone());
                        Box::new(Logger(logs))
                    })
@@ -39,17 +226,204 @@ impl Logger {
 
 impl Log for Logger {
     fn enabled(&self, metadata: &LogMetadata) -> bool {
-        metadata.level() <= LogLevel::Info
+        let buffer = self.0.buffer.lock().unwrap();
+        metadata.level() <= buffer.level_for(metadata.target())
     }
 
     fn log(&self, record: &LogRecord) {
-        if self.enabled(record.metadata()) {
-            let log = ShellLog {
-                level: record.level(),
-                message: format!("{}", record.args()),
-                target: format!("{}", record.target()),
-            };
-            (self.0).0.lock().unwrap().push(log);
+        let mut buffer = self.0.buffer.lock().unwrap();
+        if record.level() <= buffer.level_for(record.target()) {
+            buffer.push(ShellLog {
+                            timestamp: SystemTime::now(),
+                            level: record.level(),
+                            message: format!("{}", record.args()),
+                            target: format!("{}", record.target()),
+                        });
+        }
+    }
+}
+
+struct LogFile {
+    path: PathBuf,
+    file: BufWriter<File>,
+    size: u64,
+    last_flush: Instant,
+}
+
+impl LogFile {
+    fn open(path: PathBuf) -> io::Result<LogFile> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(LogFile {
+               path,
+               file: BufWriter::new(file),
+               size,
+               last_flush: Instant::now(),
+           })
+    }
+
+    // Called with the logger locked, only flushes now and then
+    fn write(&mut self, log: &ShellLog) -> io::Result<()> {
+        if self.size >= MAX_LOG_FILE_SIZE {
+            self.rotate()?;
+        }
+        let line = to_json_line(log);
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        let flush_delay = Duration::from_millis(LOG_FILE_FLUSH_MS);
+        if log.level <= LogLevel::Warn || self.last_flush.elapsed() >= flush_delay {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        self.file.flush()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        for index in (1..ROTATED_LOG_FILES).rev() {
+            let from = rotated_path(&self.path, index);
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, index + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        *self = LogFile::open(self.path.clone())?;
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    PathBuf::from(format!("{}.{}", path.display(), index))
+}
+
+#[derive(Serialize)]
+struct JsonLog<'a> {
+    // Seconds since the Unix epoch
+    timestamp: f64,
+    level: String,
+    target: &'a str,
+    message: &'a str,
+}
+
+fn to_json_line(log: &ShellLog) -> String {
+    let timestamp = log.timestamp
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as f64 + d.subsec_nanos() as f64 / 1_000_000_000.0)
+        .unwrap_or(0.0);
+    let json = JsonLog {
+        timestamp,
+        level: log.level.to_string(),
+        target: &log.target,
+        message: &log.message,
+    };
+    let mut line = serde_json::to_string(&json).unwrap_or_default();
+    line.push('\n');
+    line
+}
+
+/// Write logs to `path`, in the JSON lines format.
+pub fn export(logs: &[ShellLog], path: &Path) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    for log in logs {
+        file.write_all(to_json_line(log).as_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use log::{LogLevel, LogLevelFilter};
+    use std::time::SystemTime;
+    use super::*;
+
+    fn log(message: &str) -> ShellLog {
+        ShellLog {
+            timestamp: SystemTime::now(),
+            level: LogLevel::Info,
+            target: "servoshell".to_owned(),
+            message: message.to_owned(),
+        }
+    }
+
+    fn messages(logs: &[ShellLog]) -> Vec<&str> {
+        logs.iter().map(|log| log.message.as_str()).collect()
+    }
+
+    fn buffered(buffer: &LogBuffer) -> Vec<&str> {
+        buffer.logs.iter().map(|log| log.message.as_str()).collect()
+    }
+
+    #[test]
+    fn most_specific_target_wins() {
+        let targets = vec![("script".to_owned(), LogLevelFilter::Debug),
+                           ("script::dom".to_owned(), LogLevelFilter::Error)];
+        let buffer = LogBuffer::new(LogLevelFilter::Warn, &targets, 10);
+        assert_eq!(buffer.level_for("servoshell"), LogLevelFilter::Warn);
+        assert_eq!(buffer.level_for("script"), LogLevelFilter::Debug);
+        assert_eq!(buffer.level_for("script::timers"), LogLevelFilter::Debug);
+        assert_eq!(buffer.level_for("script::dom"), LogLevelFilter::Error);
+        assert_eq!(buffer.level_for("script::dom::node"), LogLevelFilter::Error);
+        // Not a child of script
+        assert_eq!(buffer.level_for("scripting"), LogLevelFilter::Warn);
+        assert_eq!(buffer.max_level(), LogLevelFilter::Debug);
+    }
+
+    #[test]
+    fn oldest_logs_are_dropped() {
+        let mut buffer = LogBuffer::new(LogLevelFilter::Info, &[], 2);
+        buffer.push(log("a"));
+        buffer.push(log("b"));
+        buffer.push(log("c"));
+        assert_eq!(buffered(&buffer), vec!["b", "c"]);
+        assert_eq!(buffer.dropped(), 1);
+    }
+
+    #[test]
+    fn shrink_capacity() {
+        let mut buffer = LogBuffer::new(LogLevelFilter::Info, &[], 3);
+        buffer.push(log("a"));
+        buffer.push(log("b"));
+        buffer.push(log("c"));
+        buffer.set_capacity(1);
+        assert_eq!(buffered(&buffer), vec!["c"]);
+        assert_eq!(buffer.dropped(), 2);
+        buffer.push(log("d"));
+        assert_eq!(buffered(&buffer), vec!["d"]);
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut buffer = LogBuffer::new(LogLevelFilter::Info, &[], 1);
+        buffer.push(log("a"));
+        buffer.set_capacity(0);
+        assert!(buffer.logs.is_empty());
+        buffer.push(log("b"));
+        assert!(buffer.logs.is_empty());
+        assert_eq!(buffer.count, 2);
+        assert_eq!(buffer.dropped(), 2);
+        assert!(buffer.pull().is_empty());
+    }
+
+    #[test]
+    fn pull_new_logs() {
+        let mut buffer = LogBuffer::new(LogLevelFilter::Info, &[], 3);
+        buffer.push(log("a"));
+        buffer.push(log("b"));
+        assert_eq!(messages(&buffer.pull()), vec!["a", "b"]);
+        assert!(buffer.pull().is_empty());
+        buffer.push(log("c"));
+        assert_eq!(messages(&buffer.pull()), vec!["c"]);
+        // 4 new logs, only the last 3 are still there
+        for message in &["d", "e", "f", "g"] {
+            buffer.push(log(message));
         }
+        assert_eq!(messages(&buffer.pull()), vec!["e", "f", "g"]);
+        assert_eq!(buffer.count, 7);
+        assert_eq!(buffer.pulled, 7);
+        assert_eq!(buffer.dropped(), 4);
     }
 }
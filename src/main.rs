@@ -27,8 +27,12 @@ extern crate objc;
 extern crate glutin;
 #[cfg(any(feature = "force-glutin", not(target_os = "macos")))]
 extern crate tinyfiledialogs;
+#[cfg(any(feature = "force-glutin", not(target_os = "macos")))]
+extern crate clipboard;
 
 
+#[cfg(target_os = "linux")]
+extern crate x11_dl;
 #[cfg(target_os = "windows")]
 extern crate winapi;
 #[cfg(target_os = "windows")]
@@ -36,41 +40,68 @@ extern crate user32;
 #[cfg(target_os = "windows")]
 extern crate gdi32;
 
+extern crate getopts;
+extern crate hyper;
+extern crate hyper_openssl;
 extern crate open;
 
+mod cli;
+mod download;
 mod traits;
 mod platform;
 mod servo;
 mod state;
+mod keys;
+mod library;
 mod logs;
+mod pages;
+mod prefs;
+mod remote;
+mod scroll;
+mod touch;
 
+use download::Downloads;
+use keys::{KeyCommand, KeyMatch, Keymap};
+use library::Library;
+use logs::ShellLogs;
+use pages::Pages;
 use platform::App;
-use servo::{Servo, ServoEvent, ServoUrl, WebRenderDebugOption};
-use state::{AppState, State, WindowState};
-use std::env::args;
+use prefs::{Preferences, ShellPreferences, StartupBehavior};
+use remote::{Remote, RemoteClient, RemoteEvent, RemoteRequest};
+use scroll::SmoothScroll;
+use servo::{BrowserId, Servo, ServoEvent, ServoUrl, WebRenderDebugOption};
+use state::{AppState, BrowserState, ContextMenu, State, WindowState};
+use std::env;
+use std::process::{self, Command};
 use std::rc::Rc;
+use touch::{Gesture, TouchEmulator, TouchGestures, TouchPoint};
 use traits::app::{AppEvent, AppCommand, AppMethods};
 use traits::view::*;
-use traits::window::{WindowEvent, WindowCommand};
+use traits::window::{LinkHintAction, WindowEvent, WindowCommand, WindowMethods};
 
-const PKG_VERSION: &'static str = env!("CARGO_PKG_VERSION");
-const PKG_NAME: &'static str = env!("CARGO_PKG_NAME");
+const MIN_PINCH_ZOOM: f32 = 1.0;
+const MAX_PINCH_ZOOM: f32 = 8.0;
 
 fn main() {
 
-    let logs = logs::Logger::init();
+    let cli = cli::parse();
 
-    info!("starting");
+    let logs = logs::Logger::init(cli.log_level, &cli.log_filters, cli.log_capacity);
+    if let (true, Some(profile)) = (cli.log_file, cli.profile.as_ref()) {
+        if let Err(e) = logs.set_file(profile) {
+            warn!("Can't write logs to {}: {}", profile.display(), e);
+        }
+    }
 
-    args()
-        .find(|arg| arg == "--version")
-        .map(|_| {
-                 println!("{} {}", PKG_NAME, PKG_VERSION);
-                 std::process::exit(0);
-             });
+    info!("starting");
 
     let resources_path = App::get_resources_path().expect("Can't find resources path");
 
+    let mut prefs = Preferences::load(cli.profile.as_ref().map(|p| p.as_path()));
+    let mut keymap = Keymap::load(cli.profile.as_ref().map(|p| p.as_path()));
+    let mut library = Library::load(cli.profile.as_ref().map(|p| p.as_path()));
+    let downloads = Downloads::new();
+
     let mut app_state = State::new(AppState::new());
     app_state.get_mut().current_window_index = Some(0);
 
@@ -82,9 +113,40 @@ fn main() {
     app_state.snapshot();
     win_state.snapshot();
 
+    if let Some((width, height)) = cli.size {
+        win.set_inner_size(width, height);
+    }
+    if let Some((x, y)) = cli.position {
+        win.set_position(x, y);
+    }
+
     let view = win.new_view().unwrap();
 
-    Servo::configure(resources_path.clone());
+    win_state.get_mut().fullscreen = cli.fullscreen;
+
+    // Servo can't change these once started, see `restart`. The ones from
+    // the command line are only enabled for this session.
+    let mut running_debug_options = prefs.get().debug_options.clone();
+    for name in &cli.debug {
+        running_debug_options.enable(name).unwrap();
+    }
+
+    let shell_resources_path = resources_path.parent().unwrap().join("shell_resources");
+
+    let pages = Pages::new().expect("Can't create the internal pages directory");
+    let userscripts = pages
+        .install_userscripts(&shell_resources_path.join("userscripts"))
+        .expect("Can't install the userscripts");
+
+    Servo::configure(resources_path.clone(),
+                     userscripts,
+                     cli.profile.clone(),
+                     cli.devtools_port,
+                     &running_debug_options);
+
+    for pref in &cli.prefs {
+        Servo::set_pref(pref);
+    }
 
     let servo = {
         let geometry = view.get_geometry();
@@ -92,92 +154,272 @@ fn main() {
         Servo::new(geometry, view.clone(), waker)
     };
 
-    let home_url = resources_path
-        .parent()
-        .unwrap()
-        .join("shell_resources")
-        .join("home.html");
+    let home_url = shell_resources_path.join("home.html");
     let home_url = ServoUrl::from_file_path(&home_url)
         .unwrap()
         .into_string();
+    // The preferences file can be edited by hand
+    let home_url = match prefs.get().home_page {
+        Some(ref page) => {
+            cli::parse_url(page).unwrap_or_else(|| {
+                                                     warn!("Invalid home page URL: {}", page);
+                                                     home_url
+                                                 })
+        }
+        None => home_url,
+    };
+
+    let startup_url = match prefs.get().startup {
+        StartupBehavior::HomePage => home_url,
+        StartupBehavior::BlankPage => "about:blank".to_owned(),
+    };
 
-    // Skip first argument (executable), and find the first
-    // argument that doesn't start with `-`
-    let url = args()
-        .skip(1)
-        .find(|arg| !arg.starts_with("-"))
-        .unwrap_or(home_url);
+    let mut urls = cli.urls.iter();
+    let url = urls.next().unwrap_or(&startup_url);
 
-    let browser = servo.new_browser(&url);
-    servo.select_browser(browser.id);
+    let browser = new_browser(&servo, prefs.get(), url)
+        .or_else(|e| {
+                     warn!("Can't open {}: {}", url, e);
+                     new_browser(&servo, prefs.get(), "about:blank")
+                 })
+        .expect("Can't create browser");
+    let fg_id = browser.id;
 
     win_state
         .get_mut()
         .tabs
         .append_new(browser)
         .expect("Can't append browser");
+
+    // Other URLs are opened in background tabs
+    for url in urls {
+        match new_browser(&servo, prefs.get(), url) {
+            Ok(browser) => {
+                win_state
+                    .get_mut()
+                    .tabs
+                    .append_new(browser)
+                    .expect("Can't append browser");
+            }
+            Err(e) => warn!("Can't open {}: {}", url, e),
+        }
+    }
+
+    servo.select_browser(fg_id);
+    servo.zoom(prefs.get().default_zoom);
+
+    apply_prefs(&servo, &mut win_state, &mut app_state, prefs.get(), &cli.debug);
+    prefs.watch(win.new_event_loop_waker());
+
+    app.render(app_state.diff(), app_state.get());
+    app_state.snapshot();
     win.render(win_state.diff(), win_state.get());
     win_state.snapshot();
 
     info!("Servo version: {}", servo.version());
 
+    let remote = if cli.remote {
+        remote::socket_path(cli.profile.as_ref().map(|p| p.as_path()))
+            .ok_or("Needs $XDG_RUNTIME_DIR in private mode".to_owned())
+            .and_then(|path| Remote::new(path, win.new_event_loop_waker()))
+            .map_err(|msg| warn!("Remote control disabled: {}", msg))
+            .ok()
+    } else {
+        None
+    };
+
+    let mut touch = TouchEmulator::new();
+    let mut gestures = TouchGestures::new();
+    let mut smooth_scroll = SmoothScroll::new(win.new_event_loop_waker());
+    // Servo's compositor has a single viewport zoom, for all the browsers.
+    // The shell keeps one per tab, and sets servo's to the selected tab's.
+    let mut viewport_zoom = 1.0;
+
     let handle_events = || {
 
         // Loop until no events are available anymore.
         loop {
 
-            let app_events = app.get_events();
-            let win_events = win.get_events();
+            let mut app_events = app.get_events();
+            let mut win_events = win.get_events();
             let view_events = view.get_events();
-            let servo_events = servo.get_events();
+            let mut servo_events = servo.get_events();
+            let remote_events = remote.as_ref().map_or(vec![], |r| r.get_events());
+            let new_prefs = prefs.get_changes();
 
             if app_events.is_empty() && win_events.is_empty() && view_events.is_empty() &&
-               servo_events.is_empty() {
+               servo_events.is_empty() && remote_events.is_empty() && new_prefs.is_none() {
                 break;
             }
 
+            if let Some(new_prefs) = new_prefs {
+                apply_prefs(&servo, &mut win_state, &mut app_state, &new_prefs, &cli.debug);
+            }
+
+            // Clients waiting for their command to be handled
+            let mut remote_replies = vec![];
+            if let Some(ref remote) = remote {
+                for event in remote_events {
+                    handle_remote_event(remote,
+                                        &win_state,
+                                        &mut win_events,
+                                        &mut app_events,
+                                        &mut remote_replies,
+                                        event);
+                }
+            }
+
+            servo_events =
+                handle_key_bindings(&mut keymap, servo_events, &mut win_events, &mut app_events);
+
+            // Resizing a window sends many geometry changes. Only the
+            // last one matters.
+            let mut geometry_changed = false;
+            for event in &win_events {
+                if let WindowEvent::GeometryDidChange = *event {
+                    geometry_changed = true;
+                }
+            }
+            for event in &view_events {
+                if let ViewEvent::GeometryDidChange = *event {
+                    geometry_changed = true;
+                }
+            }
+            if geometry_changed {
+                servo.update_geometry(view.get_geometry());
+                view.update_drawable();
+            }
+
             // FIXME: it's really annoying we need this
             let mut force_sync = false;
 
             for event in win_events {
-                if handle_win_event(&servo, &view, &mut win_state, &mut app_state, event)
+                if handle_win_event(&servo,
+                                    &app,
+                                    &*win,
+                                    &view,
+                                    &logs,
+                                    &mut library,
+                                    &downloads,
+                                    &pages,
+                                    &cli.debug,
+                                    &mut win_state,
+                                    &mut app_state,
+                                    &mut prefs,
+                                    &keymap,
+                                    event)
                            .expect("handle_win_event exception") {
                         force_sync = true;
                     }
             }
 
+            let mut quit = false;
             for event in app_events {
-                handle_app_event(&servo,
-                                 &view,
-                                 &mut win_state,
-                                 &mut app_state,
-                                 event).expect("handle_app_event exception");
+                quit |= handle_app_event(&servo,
+                                         &view,
+                                         &mut win_state,
+                                         &mut app_state,
+                                         &mut library,
+                                         event)
+                        .expect("handle_app_event exception");
+            }
+
+            for mut client in remote_replies {
+                client.send_ok();
             }
 
             for event in view_events {
-                handle_view_event(&servo, &view, &mut win_state, &mut app_state, event)
+                handle_view_event(&servo,
+                                  &view,
+                                  &mut win_state,
+                                  &mut app_state,
+                                  &mut touch,
+                                  &mut gestures,
+                                  &mut smooth_scroll,
+                                  prefs.get(),
+                                  event)
                         .expect("handle_view_event exception");
             }
 
+            if let Some((x, y, dx, dy)) = smooth_scroll.tick() {
+                servo.perform_scroll(x, y, dx, dy, TouchPhase::Moved);
+            }
+
             for event in servo_events {
-                handle_servo_event(&servo, &view, &mut win_state, &mut app_state, event)
+                handle_servo_event(&servo,
+                                   &app,
+                                   &view,
+                                   &logs,
+                                   &mut library,
+                                   &downloads,
+                                   &pages,
+                                   &cli.debug,
+                                   &mut win_state,
+                                   &mut app_state,
+                                   &mut prefs,
+                                   &keymap,
+                                   event)
                         .expect("handle_servo_event exception");
             }
 
+            if let Ok(pinch_zoom) = win_state.get().tabs.ref_fg_browser().map(|b| b.pinch_zoom) {
+                if pinch_zoom != viewport_zoom {
+                    servo.pinch_zoom(pinch_zoom / viewport_zoom);
+                    viewport_zoom = pinch_zoom;
+                }
+            }
+
+            // Servo zooms from the top left corner of the view, the smart
+            // zoom block is scrolled there
+            let zoom_scroll = win_state
+                .get()
+                .tabs
+                .ref_fg_browser()
+                .ok()
+                .and_then(|b| b.pinch_zoom_scroll);
+            if let Some((left, top)) = zoom_scroll {
+                win_state.get_mut().tabs.mut_fg_browser().unwrap().pinch_zoom_scroll = None;
+                let (margin_top, _, _, margin_left) = view.get_geometry().margins;
+                servo.perform_scroll(margin_left as i32,
+                                     margin_top as i32,
+                                     -left * viewport_zoom,
+                                     -top * viewport_zoom,
+                                     TouchPhase::Moved);
+            }
+
             if app_state.has_changed() || win_state.has_changed() {
+                save_prefs(&mut prefs, &win_state, &app_state, &cli.debug);
+                let win_diff = win_state.diff();
+                if let Some(ref remote) = remote {
+                    remote.broadcast_diff(&win_diff);
+                }
                 app.render(app_state.diff(), app_state.get());
-                win.render(win_state.diff(), win_state.get());
+                win.render(win_diff, win_state.get());
                 app_state.snapshot();
                 win_state.snapshot();
             }
 
+            let needs_restart = win_state.get().debug_options.needs_restart(&running_debug_options);
+            if quit || needs_restart {
+                if let Some(ref remote) = remote {
+                    remote.close();
+                }
+                // restart and exit don't return, nothing is dropped
+                pages.remove();
+                library.flush();
+                logs.flush();
+                if quit {
+                    process::exit(0);
+                }
+                restart(&cli, &prefs, win_state.get());
+            }
+
             servo.sync(force_sync);
         }
 
         // Here, only stuff that we know for sure won't trigger any
         // new events
 
-        // FIXME: logs will grow until pulled
         if win_state.get().logs_visible {
             win.append_logs(&logs.get_logs());
         }
@@ -187,12 +429,419 @@ fn main() {
 
     app.run(handle_events);
 
+    logs.flush();
+
+}
+
+/// Layout debug options are only read when servo starts. Relaunch
+/// ServoShell with the same profile and tabs to apply them.
+fn restart(cli: &cli::CommandLine, prefs: &Preferences, win_state: &WindowState) -> ! {
+    let exe = env::current_exe().expect("Can't find executable");
+    let mut command = Command::new(exe);
+    command.args(cli.to_args());
+    // The saved options are read from the profile. Nothing is saved in
+    // private mode.
+    for name in win_state.debug_options.enabled_layout_options() {
+        let saved = prefs.get().debug_options.is_layout_option_enabled(name);
+        if cli.profile.is_none() || !saved {
+            command.arg("--debug").arg(name);
+        }
+    }
+    for browser in win_state.tabs.alive_browsers() {
+        if let Some(ref url) = browser.url {
+            command.arg(url);
+        }
+    }
+    info!("Restarting to apply layout debug options");
+    if let Err(e) = command.spawn() {
+        error!("Can't restart: {}", e);
+    }
+    process::exit(0);
+}
+
+fn new_browser(servo: &Servo,
+               prefs: &ShellPreferences,
+               url: &str)
+               -> Result<BrowserState, &'static str> {
+    let mut browser = servo.new_browser(url)?;
+    browser.zoom = prefs.default_zoom;
+    Ok(browser)
+}
+
+/// Reflect the preferences in the shell state.
+/// `session_debug` are the layout debug options enabled from the command
+/// line, they stay enabled.
+fn apply_prefs(servo: &Servo,
+               win_state: &mut State<WindowState>,
+               app_state: &mut State<AppState>,
+               prefs: &ShellPreferences,
+               session_debug: &[String]) {
+    if app_state.get().dark_theme != prefs.dark_theme {
+        app_state.get_mut().dark_theme = prefs.dark_theme;
+    }
+    if win_state.get().logs_visible != prefs.logs_visible {
+        win_state.get_mut().logs_visible = prefs.logs_visible;
+    }
+    let mut new = prefs.debug_options.clone();
+    for name in session_debug {
+        new.enable(name).ok();
+    }
+    if win_state.get().debug_options != new {
+        {
+            let old = &win_state.get().debug_options;
+            if old.wr_profiler != new.wr_profiler {
+                servo.toggle_webrender_debug_option(WebRenderDebugOption::Profiler);
+            }
+            if old.wr_texture_cache_debug != new.wr_texture_cache_debug {
+                servo.toggle_webrender_debug_option(WebRenderDebugOption::TextureCacheDebug);
+            }
+            if old.wr_render_target_debug != new.wr_render_target_debug {
+                servo.toggle_webrender_debug_option(WebRenderDebugOption::RenderTargetDebug);
+            }
+        }
+        win_state.get_mut().debug_options = new;
+    }
+}
+
+/// Save the options that can be changed from the UI, except the layout
+/// debug options from the command line.
+fn save_prefs(prefs: &mut Preferences,
+              win_state: &State<WindowState>,
+              app_state: &State<AppState>,
+              session_debug: &[String]) {
+    let mut new = prefs.get().clone();
+    new.dark_theme = app_state.get().dark_theme;
+    new.logs_visible = win_state.get().logs_visible;
+    new.debug_options = win_state.get().debug_options.clone();
+    for name in session_debug {
+        let saved = prefs.get().debug_options.is_layout_option_enabled(name);
+        new.debug_options.set_layout_option(name, saved).ok();
+    }
+    prefs.set(new);
+}
+
+/// Route the servoshell:// URLs. See pages.rs.
+fn handle_internal_url(servo: &Servo,
+                       app: &App,
+                       win_state: &mut State<WindowState>,
+                       app_state: &mut State<AppState>,
+                       prefs: &mut Preferences,
+                       keymap: &Keymap,
+                       logs: &ShellLogs,
+                       library: &mut Library,
+                       downloads: &Downloads,
+                       pages: &Pages,
+                       session_debug: &[String],
+                       id: BrowserId,
+                       url: ServoUrl)
+                       -> Result<(), &'static str> {
+    match url.host_str() {
+        // The userscripts run in web pages, their requests must carry the
+        // session token
+        Some("hints") | Some("contextmenu") | Some("zoom") if !pages.has_token(&url) => {
+            warn!("Userscript request without the session token: {}", url);
+            return Ok(());
+        }
+        Some("hints") => return handle_link_hint(servo, app, win_state, prefs, id, &url),
+        Some("contextmenu") => return handle_context_menu(win_state, id, &url),
+        Some("zoom") => return handle_smart_zoom(win_state, id, &url),
+        // Only opens fixed URLs, linked from the home page
+        Some("issue") => {
+            let target = match url.path() {
+                "/servo" => pages::SERVO_ISSUE_URL,
+                "/servoshell" => pages::SHELL_ISSUE_URL,
+                _ => {
+                    warn!("Unknown issue alias: {}", url);
+                    return Ok(());
+                }
+            };
+            open::that(target).ok();
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let current = win_state.get_mut().tabs.find_browser(&id).and_then(|b| b.url.clone());
+    let action = url.path().trim_matches('/').to_owned();
+    if !action.is_empty() {
+        if !current.as_ref().map_or(false, |page| pages.is_internal(page)) {
+            warn!("Internal action not requested by an internal page: {}", url);
+            return Ok(());
+        }
+        match (url.host_str(), action.as_str()) {
+            (Some("settings"), "save") => {
+                let new = pages::settings_from_query(&url, prefs.get());
+                apply_prefs(servo, win_state, app_state, &new, session_debug);
+                prefs.set(new);
+            }
+            (Some("history"), "clear") => library.clear_history(),
+            (Some("history"), "bookmark") => {
+                if let Some(bookmark) = query_param(&url, "url") {
+                    let title = library
+                        .history()
+                        .iter()
+                        .rev()
+                        .find(|entry| entry.url == bookmark)
+                        .and_then(|entry| entry.title.clone());
+                    library.add_bookmark(&bookmark, title);
+                }
+            }
+            (Some("bookmarks"), "remove") => {
+                if let Some(bookmark) = query_param(&url, "url") {
+                    library.remove_bookmark(&bookmark);
+                }
+            }
+            (Some("logs"), "record") => pages::apply_log_settings(&url, logs),
+            (Some("downloads"), "open") => {
+                let index = query_param(&url, "index").and_then(|i| i.parse::<usize>().ok());
+                match index.and_then(|index| downloads.list().get(index).cloned()) {
+                    Some(download) => {
+                        open::that(&download.path).ok();
+                    }
+                    None => warn!("Unknown download: {}", url),
+                }
+            }
+            _ => {
+                warn!("Unknown internal action: {}", url);
+                return Ok(());
+            }
+        }
+    }
+
+    let (name, html) = match url.host_str() {
+        Some("about") => ("about", pages::about(&servo.version(), library.profile())),
+        Some("history") => ("history", pages::history(library.history(), library.bookmarks())),
+        Some("bookmarks") => ("bookmarks", pages::bookmarks(library.bookmarks())),
+        Some("downloads") => ("downloads", pages::downloads(&downloads.list())),
+        Some("settings") => ("settings", pages::settings(prefs.get(), action == "save")),
+        Some("keys") => ("keys", pages::keys(keymap)),
+        Some("logs") => {
+            // After an action, show all the logs, and update the page with
+            // the logs URL, not the action's
+            let url = if action.is_empty() {
+                url.clone()
+            } else {
+                ServoUrl::parse(pages::LOGS_URL).unwrap()
+            };
+            let filter = pages::LogFilter::from_query(&url);
+            ("logs", pages::logs(logs, &filter, &url))
+        }
+        _ => {
+            warn!("Unknown internal page: {}", url);
+            return Ok(());
+        }
+    };
+    match pages.write_page(id, name, &html) {
+        // Updating the page the browser shows, after an action or when the
+        // logs page asks for new logs. Reloading doesn't add an history entry.
+        Ok(page_url) => {
+            if current == Some(page_url.as_str().to_owned()) {
+                servo.reload(id);
+            } else {
+                servo.load_url(id, page_url);
+            }
+        }
+        Err(err) => warn!("Can't write internal page: {}", err),
+    }
+    Ok(())
+}
+
+/// Turn the key events matching a key binding into commands. Returns the
+/// other events. Keys are first sent to the page, servo only gives us back
+/// the ones the page didn't prevent, so pages can override key bindings.
+fn handle_key_bindings(keymap: &mut Keymap,
+                       events: Vec<ServoEvent>,
+                       win_events: &mut Vec<WindowEvent>,
+                       app_events: &mut Vec<AppEvent>)
+                       -> Vec<ServoEvent> {
+    events
+        .into_iter()
+        .filter(|event| match *event {
+                    ServoEvent::Key(c, key, modifiers) => {
+                        match keymap.handle_key(c, key, KeyState::Pressed, modifiers) {
+                            KeyMatch::Command(KeyCommand::Window(command)) => {
+                                win_events.push(WindowEvent::DoCommand(command));
+                                false
+                            }
+                            KeyMatch::Command(KeyCommand::App(command)) => {
+                                app_events.push(AppEvent::DoCommand(command));
+                                false
+                            }
+                            KeyMatch::Consumed => false,
+                            KeyMatch::Unhandled => true,
+                        }
+                    }
+                    _ => true,
+                })
+        .collect()
+}
+
+fn query_param(url: &ServoUrl, name: &str) -> Option<String> {
+    url.as_url()
+        .query_pairs()
+        .find(|&(ref key, _)| *key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// Right click, sent by shell_resources/userscripts/contextmenu.js.
+fn handle_context_menu(win_state: &mut State<WindowState>,
+                       id: BrowserId,
+                       url: &ServoUrl)
+                       -> Result<(), &'static str> {
+    let menu = {
+        let fg = win_state.get().tabs.ref_fg_browser()?;
+        if fg.id != id {
+            return Ok(());
+        }
+        // Only reported by the userscript, the page can't be trusted
+        let param = |name: &str| {
+            query_param(url, name)
+                .and_then(|value| ServoUrl::parse(&value).ok())
+                .map(|value| value.into_string())
+        };
+        ContextMenu::new(param("link"), param("image"), fg)
+    };
+    win_state.get_mut().context_menu = Some(menu);
+    Ok(())
+}
+
+/// Size of the block to zoom on, sent by shell_resources/userscripts/zoom.js.
+fn handle_smart_zoom(win_state: &mut State<WindowState>,
+                     id: BrowserId,
+                     url: &ServoUrl)
+                     -> Result<(), &'static str> {
+    let param = |name| query_param(url, name).and_then(|value| value.parse::<f32>().ok());
+    let browser = win_state.get_mut().tabs.mut_fg_browser()?;
+    if browser.id != id {
+        return Ok(());
+    }
+    match (param("width"), param("left"), param("top"), param("viewport")) {
+        (Some(width), Some(left), Some(top), Some(viewport)) if width > 0.0 => {
+            browser.pinch_zoom = clamp_pinch_zoom(viewport / width);
+            browser.pinch_zoom_scroll = Some((left, top));
+        }
+        _ => warn!("Invalid smart zoom: {}", url),
+    }
+    Ok(())
+}
+
+/// Result of a link hint, sent by shell_resources/userscripts/hints.js.
+fn handle_link_hint(servo: &Servo,
+                    app: &App,
+                    win_state: &mut State<WindowState>,
+                    prefs: &Preferences,
+                    id: BrowserId,
+                    url: &ServoUrl)
+                    -> Result<(), &'static str> {
+    let param = |name: &str| query_param(url, name);
+    let zoom = {
+        let fg = win_state.get().tabs.ref_fg_browser()?;
+        if fg.id != id {
+            return Ok(());
+        }
+        fg.zoom
+    };
+    match (url.path(), param("url")) {
+        ("/click", _) => {
+            let x = param("x").and_then(|x| x.parse().ok());
+            let y = param("y").and_then(|y| y.parse().ok());
+            match (x, y) {
+                (Some(x), Some(y)) => servo.perform_page_click(x, y, zoom),
+                _ => warn!("Invalid link hint: {}", url),
+            }
+        }
+        ("/open", Some(target)) => {
+            match ServoUrl::parse(&target) {
+                Ok(target) => open_in_background(servo, win_state, prefs, target.as_str())?,
+                Err(_) => warn!("Invalid link hint: {}", url),
+            }
+        }
+        ("/copy", Some(target)) => copy_to_clipboard(app, &target),
+        _ => warn!("Invalid link hint: {}", url),
+    }
+    Ok(())
+}
+
+fn open_in_background(servo: &Servo,
+                      win_state: &mut State<WindowState>,
+                      prefs: &Preferences,
+                      url: &str)
+                      -> Result<(), &'static str> {
+    let fg = win_state.get().tabs.ref_fg_browser()?.id;
+    let mut browser = match new_browser(servo, prefs.get(), url) {
+        Ok(browser) => browser,
+        Err(e) => {
+            warn!("Can't open {}: {}", url, e);
+            return Ok(());
+        }
+    };
+    browser.is_background = true;
+    win_state.get_mut().tabs.append_new(browser)?;
+    // new_browser selects the new browser
+    servo.select_browser(fg);
+    Ok(())
+}
+
+/// Open an about:blank tab in the foreground.
+fn open_new_tab(servo: &Servo,
+                view: &Rc<ViewMethods>,
+                win_state: &mut State<WindowState>,
+                prefs: &Preferences)
+                -> Result<BrowserId, &'static str> {
+    let mut browser = new_browser(servo, prefs.get(), "about:blank")?;
+    browser.is_background = false;
+    win_state.get_mut().tabs.append_new(browser)?;
+    let id = win_state.get().tabs.ref_fg_browser()?.id;
+    servo.select_browser(id);
+    servo.update_geometry(view.get_geometry());
+    servo.zoom(prefs.get().default_zoom);
+    Ok(id)
+}
+
+fn copy_to_clipboard(app: &App, text: &str) {
+    if let Err(e) = app.set_clipboard(text) {
+        warn!("Can't copy to clipboard: {}", e);
+    }
+}
+
+fn handle_remote_event(remote: &Remote,
+                       win_state: &State<WindowState>,
+                       win_events: &mut Vec<WindowEvent>,
+                       app_events: &mut Vec<AppEvent>,
+                       replies: &mut Vec<RemoteClient>,
+                       event: RemoteEvent) {
+    let RemoteEvent { request, mut client } = event;
+    match request {
+        RemoteRequest::WindowCommand(cmd) => {
+            win_events.push(WindowEvent::DoCommand(cmd));
+            replies.push(client);
+        }
+        RemoteRequest::AppCommand(cmd) => {
+            app_events.push(AppEvent::DoCommand(cmd));
+            replies.push(client);
+        }
+        RemoteRequest::GetWindowState => {
+            client.send_state(win_state.get());
+        }
+        RemoteRequest::Subscribe => {
+            remote.subscribe(client);
+        }
+    }
 }
 
 fn handle_win_event(servo: &Servo,
+                    app: &App,
+                    win: &WindowMethods,
                     view: &Rc<ViewMethods>,
+                    logs: &ShellLogs,
+                    library: &mut Library,
+                    downloads: &Downloads,
+                    pages: &Pages,
+                    session_debug: &[String],
                     win_state: &mut State<WindowState>,
-                    _app_state: &mut State<AppState>,
+                    app_state: &mut State<AppState>,
+                    prefs: &mut Preferences,
+                    keymap: &Keymap,
                     event: WindowEvent)
                     -> Result<bool, &'static str> {
 
@@ -201,14 +850,17 @@ fn handle_win_event(servo: &Servo,
             return Ok(true);
         }
         WindowEvent::GeometryDidChange => {
-            servo.update_geometry(view.get_geometry());
-            view.update_drawable();
+            // Handled once per loop, see handle_events
         }
         WindowEvent::DidEnterFullScreen => {
-            // FIXME
+            win_state.get_mut().fullscreen = true;
+            servo.update_geometry(view.get_geometry());
+            view.update_drawable();
         }
         WindowEvent::DidExitFullScreen => {
-            // FIXME
+            win_state.get_mut().fullscreen = false;
+            servo.update_geometry(view.get_geometry());
+            view.update_drawable();
         }
         WindowEvent::WillClose => {
             // FIXME
@@ -216,6 +868,9 @@ fn handle_win_event(servo: &Servo,
         WindowEvent::OptionsClosed => {
             win_state.get_mut().options_open = false;
         }
+        WindowEvent::ContextMenuClosed => {
+            win_state.get_mut().context_menu = None;
+        }
         WindowEvent::UrlbarFocusChanged(focused) => {
             win_state
                 .get_mut()
@@ -259,8 +914,17 @@ fn handle_win_event(servo: &Servo,
                     servo.zoom(win_state.get().tabs.ref_fg_browser()?.zoom);
                 }
                 WindowCommand::ZoomToActualSize => {
-                    win_state.get_mut().tabs.mut_fg_browser()?.zoom = 1.0;
-                    servo.reset_zoom();
+                    let zoom = prefs.get().default_zoom;
+                    win_state.get_mut().tabs.mut_fg_browser()?.zoom = zoom;
+                    if zoom == 1.0 {
+                        servo.reset_zoom();
+                    } else {
+                        servo.zoom(zoom);
+                    }
+                }
+
+                WindowCommand::ResetPinchZoom => {
+                    win_state.get_mut().tabs.mut_fg_browser()?.pinch_zoom = 1.0;
                 }
 
                 WindowCommand::ToggleSidebar => {
@@ -279,37 +943,93 @@ fn handle_win_event(servo: &Servo,
                         .mut_fg_browser()?
                         .urlbar_focused = false;
                     let url = ServoUrl::parse(&request)
-                        .or_else(|error| {
-                            // See: https://github.com/paulrouget/servoshell/issues/59
-                            if request.ends_with(".com") || request.ends_with(".org") ||
-                               request.ends_with(".net") {
-                                ServoUrl::parse(&format!("http://{}", request))
-                            } else {
-                                Err(error)
-                            }
-                        })
-                        .or_else(|_| {
-                                     ServoUrl::parse(&format!("https://duckduckgo.com/html/?q={}",
-                                                              request))
-                                 });
+                        .or_else(|error| cli::parse_host(&request).ok_or(error))
+                        .or_else(|_| ServoUrl::parse(&prefs.get().search_url(&request)));
                     match url {
+                        Ok(ref url) if url.scheme() == "servoshell" => {
+                            let url = url.clone();
+                            handle_internal_url(servo,
+                                                app,
+                                                win_state,
+                                                app_state,
+                                                prefs,
+                                                keymap,
+                                                logs,
+                                                library,
+                                                downloads,
+                                                pages,
+                                                session_debug,
+                                                bid,
+                                                url)?;
+                        }
                         Ok(url) => servo.load_url(bid, url),
                         Err(err) => warn!("Can't parse url: {}", err),
                     }
                 }
+                WindowCommand::ToggleBookmark => {
+                    let browser = win_state.get().tabs.ref_fg_browser()?;
+                    if let Some(ref url) = browser.url {
+                        if library.is_bookmarked(url) {
+                            library.remove_bookmark(url);
+                            info!("Removed {} from the bookmarks", url);
+                        } else {
+                            library.add_bookmark(url, browser.title.clone());
+                            info!("Bookmarked {}", url);
+                        }
+                    }
+                }
+                WindowCommand::ToggleFullscreen => {
+                    win_state.get_mut().fullscreen = !win_state.get().fullscreen;
+                }
+                WindowCommand::ExportLogs => {
+                    if let Some(path) = win.choose_save_path("servoshell-logs.jsonl") {
+                        match logs::export(&logs.all_logs(), &path) {
+                            Ok(()) => info!("Logs saved to {}", path.display()),
+                            Err(e) => warn!("Can't save logs to {}: {}", path.display(), e),
+                        }
+                    }
+                }
                 WindowCommand::ToggleOptionShowLogs => {
-                    win_state.get_mut().logs_visible = !win_state.get().logs_visible;
+                    if cfg!(all(not(feature = "force-glutin"), target_os = "macos")) {
+                        win_state.get_mut().logs_visible = !win_state.get().logs_visible;
+                    } else {
+                        // No log panel on glutin, the logs are shown in a tab,
+                        // reused if it's already open
+                        let logs_tab = {
+                            let shows_logs = |b: &&BrowserState| match b.url {
+                                Some(ref url) => pages.shows_page(b.id, "logs", url),
+                                None => false,
+                            };
+                            win_state.get().tabs.alive_browsers().iter().position(shows_logs)
+                        };
+                        if let Some(idx) = logs_tab {
+                            win_state.get_mut().tabs.select_nth(idx)?;
+                            let id = win_state.get().tabs.ref_fg_browser()?.id;
+                            servo.select_browser(id);
+                        } else {
+                            let id = open_new_tab(servo, view, win_state, prefs)?;
+                            let url = ServoUrl::parse(pages::LOGS_URL).unwrap();
+                            handle_internal_url(servo,
+                                                app,
+                                                win_state,
+                                                app_state,
+                                                prefs,
+                                                keymap,
+                                                logs,
+                                                library,
+                                                downloads,
+                                                pages,
+                                                session_debug,
+                                                id,
+                                                url)?;
+                        }
+                    }
                 }
                 WindowCommand::NewTab => {
-                    let mut browser = servo.new_browser("about:blank");
-                    browser.is_background = false;
+                    open_new_tab(servo, view, win_state, prefs)?;
                     if cfg!(all(not(feature = "force-glutin"), target_os = "macos")) {
-                        browser.urlbar_focused = true;
+                        win_state.get_mut().tabs.mut_fg_browser()?.urlbar_focused = true;
                     }
-                    win_state.get_mut().tabs.append_new(browser)?;
-                    let new = win_state.get().tabs.ref_fg_browser()?.id;
-                    servo.select_browser(new);
-                    servo.update_geometry(view.get_geometry());
                 }
                 WindowCommand::CloseTab => {
                     if win_state.get().tabs.has_more_than_one() {
@@ -348,11 +1068,26 @@ fn handle_win_event(servo: &Servo,
                         servo.select_browser(new);
                     }
                 }
-                WindowCommand::ToggleOptionFragmentBorders => {}
-                WindowCommand::ToggleOptionParallelDisplayListBuidling => {}
-                WindowCommand::ToggleOptionShowParallelLayout => {}
-                WindowCommand::ToggleOptionConvertMouseToTouch => {}
-                WindowCommand::ToggleOptionTileBorders => {}
+                WindowCommand::ToggleOptionFragmentBorders => {
+                    win_state.get_mut().debug_options.show_fragment_borders =
+                        !win_state.get().debug_options.show_fragment_borders;
+                }
+                WindowCommand::ToggleOptionParallelDisplayListBuidling => {
+                    win_state.get_mut().debug_options.parallel_display_list_building =
+                        !win_state.get().debug_options.parallel_display_list_building;
+                }
+                WindowCommand::ToggleOptionShowParallelLayout => {
+                    win_state.get_mut().debug_options.show_parallel_layout =
+                        !win_state.get().debug_options.show_parallel_layout;
+                }
+                WindowCommand::ToggleOptionConvertMouseToTouch => {
+                    win_state.get_mut().debug_options.convert_mouse_to_touch =
+                        !win_state.get().debug_options.convert_mouse_to_touch;
+                }
+                WindowCommand::ToggleOptionTileBorders => {
+                    win_state.get_mut().debug_options.show_tiles_borders =
+                        !win_state.get().debug_options.show_tiles_borders;
+                }
 
                 WindowCommand::ToggleOptionWRProfiler => {
                     win_state.get_mut().debug_options.wr_profiler =
@@ -371,6 +1106,34 @@ fn handle_win_event(servo: &Servo,
                         !win_state.get().debug_options.wr_render_target_debug;
                     servo.toggle_webrender_debug_option(WebRenderDebugOption::RenderTargetDebug);
                 }
+                WindowCommand::ShowLinkHints(action) => {
+                    // The hints userscript listens for these keys
+                    let key = match action {
+                        LinkHintAction::Click => Key::F,
+                        LinkHintAction::OpenInNewTab => Key::N,
+                        LinkHintAction::CopyUrl => Key::Y,
+                    };
+                    send_userscript_key(servo, bid, key);
+                }
+                WindowCommand::OpenInNewTab(url) => {
+                    open_in_background(servo, win_state, prefs, &url)?;
+                }
+                WindowCommand::CopyToClipboard(text) => {
+                    copy_to_clipboard(app, &text);
+                }
+                WindowCommand::SaveImage(url) => {
+                    match ServoUrl::parse(&url) {
+                        Ok(url) => downloads.save(url),
+                        Err(err) => warn!("Can't parse url: {}", err),
+                    }
+                }
+                WindowCommand::ViewSource => {
+                    let url = win_state.get().tabs.ref_fg_browser()?.url.clone();
+                    if let Some(url) = url {
+                        let source = format!("view-source:{}", url);
+                        open_in_background(servo, win_state, prefs, &source)?;
+                    }
+                }
             }
         }
     }
@@ -378,19 +1141,23 @@ fn handle_win_event(servo: &Servo,
 }
 
 
+/// Returns true if the app must exit.
 fn handle_app_event(servo: &Servo,
                     view: &Rc<ViewMethods>,
                     _win_state: &mut State<WindowState>,
                     app_state: &mut State<AppState>,
+                    library: &mut Library,
                     event: AppEvent)
-                    -> Result<(), &'static str> {
+                    -> Result<bool, &'static str> {
 
     match event {
         AppEvent::DidFinishLaunching => {
             // FIXME: does this work?
         }
         AppEvent::WillTerminate => {
-            // FIXME: does this work?
+            // The app cancelled its termination, so the pending history
+            // and bookmarks are written before exiting
+            return Ok(true);
         }
         AppEvent::DidChangeScreenParameters => {
             // FIXME: does this work?
@@ -400,7 +1167,7 @@ fn handle_app_event(servo: &Servo,
         AppEvent::DoCommand(cmd) => {
             match cmd {
                 AppCommand::ClearHistory => {
-                    // FIXME
+                    library.clear_history();
                 }
                 AppCommand::ToggleOptionDarkTheme => {
                     app_state.get_mut().dark_theme = !app_state.get().dark_theme;
@@ -408,7 +1175,7 @@ fn handle_app_event(servo: &Servo,
             }
         }
     };
-    Ok(())
+    Ok(false)
 }
 
 
@@ -417,73 +1184,169 @@ fn handle_view_event(servo: &Servo,
                      view: &Rc<ViewMethods>,
                      win_state: &mut State<WindowState>,
                      _app_state: &mut State<AppState>,
+                     touch: &mut TouchEmulator,
+                     gestures: &mut TouchGestures,
+                     smooth_scroll: &mut SmoothScroll,
+                     prefs: &ShellPreferences,
                      event: ViewEvent)
                      -> Result<(), &'static str> {
 
+    let mouse_to_touch = win_state.get().debug_options.convert_mouse_to_touch;
+
     match event {
         ViewEvent::GeometryDidChange => {
-            servo.update_geometry(view.get_geometry());
-            view.update_drawable();
+            // Handled once per loop, see handle_events
         }
-        ViewEvent::MouseWheel(delta, phase) => {
-            // FIXME: magic value
-            static LINE_HEIGHT: f32 = 38.0;
-            let (mut x, mut y) = match delta {
-                MouseScrollDelta::PixelDelta(x, y) => (x, y),
-                MouseScrollDelta::LineDelta(x, y) => (x, y * LINE_HEIGHT),
+        ViewEvent::MouseWheel(delta, phase, x, y) => {
+            let (mut dx, mut dy, by_line) = match delta {
+                MouseScrollDelta::PixelDelta(dx, dy) => (dx, dy, false),
+                MouseScrollDelta::LineDelta(dx, dy) => {
+                    (dx * prefs.line_height, dy * prefs.line_height, true)
+                }
             };
-            if y.abs() >= x.abs() {
-                x = 0.0;
+            if !prefs.diagonal_scrolling {
+                if dy.abs() >= dx.abs() {
+                    dx = 0.0;
+                } else {
+                    dy = 0.0;
+                }
+            }
+            if by_line && prefs.smooth_scrolling {
+                smooth_scroll.scroll(x, y, dx, dy);
             } else {
-                y = 0.0;
+                smooth_scroll.stop();
+                servo.perform_scroll(x, y, dx, dy, phase);
+            }
+        }
+        ViewEvent::MouseMoved(x, y) if mouse_to_touch => {
+            for point in touch.mouse_moved(x, y) {
+                handle_touch(servo, win_state, gestures, point)?;
             }
-            servo.perform_scroll(0, 0, x, y, phase);
         }
         ViewEvent::MouseMoved(x, y) => {
             servo.perform_mouse_move(x, y);
         }
+        ViewEvent::MouseInput(element_state, MouseButton::Left, x, y) if mouse_to_touch => {
+            for point in touch.mouse_input(element_state, MouseButton::Left, x, y) {
+                handle_touch(servo, win_state, gestures, point)?;
+            }
+        }
+        ViewEvent::Touch(id, phase, x, y) => {
+            handle_touch(servo, win_state, gestures, TouchPoint { id, phase, x, y })?;
+        }
+        ViewEvent::Magnify(magnification) => {
+            pinch_zoom(win_state, magnification)?;
+        }
+        ViewEvent::SmartZoom(x, y) => {
+            smart_zoom(servo, win_state, x, y)?;
+        }
         ViewEvent::MouseInput(element_state, button, x, y) => {
+            if element_state == ElementState::Pressed &&
+               win_state.get().tabs.ref_fg_browser()?.urlbar_focused {
+                win_state
+                    .get_mut()
+                    .tabs
+                    .mut_fg_browser()?
+                    .urlbar_focused = false;
+            }
             servo.perform_click(x, y, element_state, button);
         }
         ViewEvent::KeyEvent(c, key, keystate, modifiers) => {
-            let id = win_state
-                .get()
-                .tabs
-                .ref_fg_browser()
-                .expect("no current browser")
-                .id;
-            servo.send_key(id, c, key, keystate, modifiers);
+            touch.set_modifiers(modifiers);
+            // Escape always leaves fullscreen, even if the page prevents it
+            if key == Key::Escape && keystate == KeyState::Pressed && win_state.get().fullscreen {
+                win_state.get_mut().fullscreen = false;
+            }
+            let browser = win_state.get().tabs.ref_fg_browser()?;
+            if !browser.urlbar_focused {
+                servo.send_key(browser.id, c, key, keystate, modifiers);
+            }
         }
     };
     Ok(())
 }
 
+fn handle_touch(servo: &Servo,
+                win_state: &mut State<WindowState>,
+                gestures: &mut TouchGestures,
+                point: TouchPoint)
+                -> Result<(), &'static str> {
+    for gesture in gestures.handle_touch(point) {
+        match gesture {
+            Gesture::Touch(point) => servo.perform_touch(point.id, point.phase, point.x, point.y),
+            Gesture::Pinch(magnification) => pinch_zoom(win_state, magnification)?,
+            Gesture::DoubleTap(x, y) => smart_zoom(servo, win_state, x, y)?,
+        }
+    }
+    Ok(())
+}
 
+fn pinch_zoom(win_state: &mut State<WindowState>, magnification: f32) -> Result<(), &'static str> {
+    let browser = win_state.get_mut().tabs.mut_fg_browser()?;
+    browser.pinch_zoom = clamp_pinch_zoom(browser.pinch_zoom * magnification);
+    Ok(())
+}
+
+fn clamp_pinch_zoom(zoom: f32) -> f32 {
+    zoom.max(MIN_PINCH_ZOOM).min(MAX_PINCH_ZOOM)
+}
+
+/// Zoom in on the block at (x, y), or zoom out if already zoomed in.
+fn smart_zoom(servo: &Servo,
+              win_state: &mut State<WindowState>,
+              x: i32,
+              y: i32)
+              -> Result<(), &'static str> {
+    let browser = win_state.get_mut().tabs.mut_fg_browser()?;
+    if browser.pinch_zoom != 1.0 {
+        browser.pinch_zoom = 1.0;
+        return Ok(());
+    }
+    // The zoom userscript looks for the block under the pointer
+    servo.perform_mouse_move(x, y);
+    send_userscript_key(servo, browser.id, Key::Z);
+    Ok(())
+}
 
-fn handle_servo_event(_servo: &Servo,
+/// Userscripts listen for keys pressed with all the modifiers, which
+/// can't be typed.
+fn send_userscript_key(servo: &Servo, id: BrowserId, key: Key) {
+    let modifiers = KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT |
+                    KeyModifiers::SUPER;
+    servo.send_key(id, None, key, KeyState::Pressed, modifiers);
+    servo.send_key(id, None, key, KeyState::Released, modifiers);
+}
+
+fn handle_servo_event(servo: &Servo,
+                      app: &App,
                       view: &Rc<ViewMethods>,
+                      logs: &ShellLogs,
+                      library: &mut Library,
+                      downloads: &Downloads,
+                      pages: &Pages,
+                      session_debug: &[String],
                       win_state: &mut State<WindowState>,
                       app_state: &mut State<AppState>,
+                      prefs: &mut Preferences,
+                      keymap: &Keymap,
                       event: ServoEvent)
                       -> Result<(), &'static str> {
 
     match event {
-        ServoEvent::SetWindowInnerSize(..) => {
-            // ignore
-        }
+        ServoEvent::SetWindowInnerSize(..) |
         ServoEvent::SetWindowPosition(..) => {
-            // ignore
+            // FIXME: only windows opened by script may be moved or resized,
+            // and servo doesn't create browsers for window.open yet
         }
         ServoEvent::SetFullScreenState(fullscreen) => {
-            if fullscreen {
-                view.enter_fullscreen();
-            } else {
-                view.exit_fullscreen();
-            }
+            win_state.get_mut().fullscreen = fullscreen;
         }
         ServoEvent::TitleChanged(id, title) => {
             match win_state.get_mut().tabs.find_browser(&id) {
                 Some(browser) => {
+                    if let Some(ref url) = browser.url {
+                        library.set_title(url, title.clone());
+                    }
                     browser.title = title;
                 }
                 None => warn!("Got message for unkown browser:  {:?}", id),
@@ -515,6 +1378,9 @@ fn handle_servo_event(_servo: &Servo,
             match win_state.get_mut().tabs.find_browser(&id) {
                 Some(browser) => {
                     let url = entries[current].url.to_string();
+                    if !pages.is_internal(&url) && !url.starts_with("about:") {
+                        library.add_visit(&url);
+                    }
                     browser.url = Some(url);
                     browser.can_go_back = current > 0;
                     browser.can_go_forward = current < entries.len() - 1;
@@ -533,10 +1399,22 @@ fn handle_servo_event(_servo: &Servo,
             // FIXME
         }
         ServoEvent::Key(..) => {
-            // FIXME
+            // See handle_key_bindings
         }
-        ServoEvent::OpenInDefaultBrowser(url) => {
-            open::that(url).ok();
+        ServoEvent::InternalNavigation(id, url) => {
+            handle_internal_url(servo,
+                                app,
+                                win_state,
+                                app_state,
+                                prefs,
+                                keymap,
+                                logs,
+                                library,
+                                downloads,
+                                pages,
+                                session_debug,
+                                id,
+                                url)?;
         }
     };
     Ok(())
@@ -0,0 +1,694 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Built-in pages, served under the servoshell:// scheme.
+//!
+//! Servo can't load custom schemes, so pages are generated into a private
+//! directory created for the session, and loaded from there. Pages talk back to the shell
+//! by navigating to servoshell:// URLs, which are intercepted before
+//! reaching servo.
+//!
+//! `servoshell://<page>` shows a page, and `servoshell://<page>/<action>`
+//! asks the shell to do something, then shows the page again. Actions are
+//! only accepted from the pages of this module, web pages can't use them.
+//! The userscripts, which run in web pages, send a session token instead.
+
+use cli;
+use download::{Download, DownloadStatus};
+use keys::Keymap;
+use library::Entry;
+use log::LogLevelFilter;
+use logs::{ShellLog, ShellLogs};
+use prefs::{ShellPreferences, StartupBehavior};
+use serde_json;
+use servo::{BrowserId, ServoUrl};
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::env;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const ABOUT_URL: &'static str = "servoshell://about";
+pub const HISTORY_URL: &'static str = "servoshell://history";
+pub const BOOKMARKS_URL: &'static str = "servoshell://bookmarks";
+pub const DOWNLOADS_URL: &'static str = "servoshell://downloads";
+pub const SETTINGS_URL: &'static str = "servoshell://settings";
+pub const KEYS_URL: &'static str = "servoshell://keys";
+pub const LOGS_URL: &'static str = "servoshell://logs";
+
+pub const SHELL_ISSUE_URL: &'static str = "http://github.com/paulrouget/servoshell/issues/new";
+pub const SERVO_ISSUE_URL: &'static str = "http://github.com/servo/servo/issues/new";
+
+// Replaced by the session token in the userscripts
+const TOKEN_PLACEHOLDER: &'static str = "__SERVOSHELL_TOKEN__";
+
+// The logs page reloads itself this often, unless paused
+const LOGS_REFRESH_MS: u32 = 2000;
+
+const STYLE: &'static str = "
+  body { margin: 50px; font-family: Helvetica; font-weight: lighter; }
+  h1 { font-weight: lighter; }
+  label { display: block; margin: 8px 0; }
+  input[type=text] { width: 400px; }
+  fieldset { border: none; padding: 0; margin: 20px 0; }
+  td { padding: 4px 20px 4px 0; }
+  code { font-size: 14px; }
+  nav a { margin-right: 15px; }
+  .url { color: #999; font-size: 12px; }
+";
+
+const LOGS_STYLE: &'static str = "
+  body { margin: 20px; }
+  form label { display: inline-block; margin-right: 20px; }
+  form input[type=text] { width: 200px; }
+  table { border-collapse: collapse; font-family: monospace; font-size: 12px; }
+  td { padding: 2px 10px 2px 0; vertical-align: top; white-space: pre-wrap; }
+  .ERROR { color: #c00; font-weight: bold; }
+  .WARN { color: #c60; }
+  .INFO { color: #06c; }
+  .DEBUG { color: #666; }
+  .TRACE { color: #999; }
+";
+
+/// A private directory for this session, where the pages are written and
+/// the userscripts are installed, and the token the userscripts send back
+/// with their servoshell:// requests so web pages can't make the same
+/// requests.
+pub struct Pages {
+    dir: PathBuf,
+    token: String,
+}
+
+impl Pages {
+    pub fn new() -> io::Result<Pages> {
+        let dir = env::temp_dir().join(format!("servoshell-{}", random_hex()));
+        create_private_dir(&dir)?;
+        create_private_dir(&dir.join("pages"))?;
+        Ok(Pages {
+               dir,
+               token: random_hex(),
+           })
+    }
+
+    /// Copy the userscripts of `from`, with the token, and return the
+    /// directory servo should load them from.
+    pub fn install_userscripts(&self, from: &Path) -> io::Result<PathBuf> {
+        let dir = self.dir.join("userscripts");
+        create_private_dir(&dir)?;
+        for entry in fs::read_dir(from)? {
+            let path = entry?.path();
+            let name = match path.file_name() {
+                Some(name) if path.extension().map_or(false, |ext| ext == "js") => name,
+                _ => continue,
+            };
+            let mut script = String::new();
+            File::open(&path)?.read_to_string(&mut script)?;
+            let script = script.replace(TOKEN_PLACEHOLDER, &self.token);
+            File::create(dir.join(name))?.write_all(script.as_bytes())?;
+        }
+        Ok(dir)
+    }
+
+    /// Whether a servoshell:// request comes from the userscripts.
+    pub fn has_token(&self, url: &ServoUrl) -> bool {
+        url.as_url()
+            .query_pairs()
+            .any(|(key, value)| key == "token" && value == self.token.as_str())
+    }
+
+    /// Whether `url` is one of the pages written by `write_page`.
+    pub fn is_internal(&self, url: &str) -> bool {
+        match ServoUrl::from_file_path(self.dir.join("pages")) {
+            Ok(dir) => url.starts_with(&format!("{}/", dir)),
+            Err(_) => false,
+        }
+    }
+
+    /// Write the page shown in a browser and return its URL. Each browser
+    /// has its own copy of a page.
+    pub fn write_page(&self, id: BrowserId, name: &str, html: &str) -> Result<ServoUrl, String> {
+        let path = self.page_path(id, name);
+        File::create(&path)
+            .and_then(|mut file| file.write_all(html.as_bytes()))
+            .map_err(|e| e.to_string())?;
+        ServoUrl::from_file_path(&path).map_err(|_| format!("Invalid path: {}", path.display()))
+    }
+
+    /// Whether a browser shows the `name` page.
+    pub fn shows_page(&self, id: BrowserId, name: &str, url: &str) -> bool {
+        ServoUrl::from_file_path(self.page_path(id, name))
+            .map_or(false, |page| page.as_str() == url)
+    }
+
+    fn page_path(&self, id: BrowserId, name: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        id.hash(&mut hasher);
+        self.dir.join("pages").join(format!("{}-{:x}.html", name, hasher.finish()))
+    }
+
+    pub fn remove(&self) {
+        fs::remove_dir_all(&self.dir).ok();
+    }
+}
+
+impl Drop for Pages {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+#[cfg(unix)]
+fn create_private_dir(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    // Fails if the directory exists, someone else could own it
+    fs::DirBuilder::new().mode(0o700).create(path)
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(path: &Path) -> io::Result<()> {
+    fs::create_dir(path)
+}
+
+// RandomState keys come from the OS random number generator
+fn random_hex() -> String {
+    (0..2)
+        .map(|_| format!("{:016x}", RandomState::new().build_hasher().finish()))
+        .collect()
+}
+
+fn page(title: &str, body: &str) -> String {
+    let nav = [(ABOUT_URL, "About"),
+               (HISTORY_URL, "History"),
+               (BOOKMARKS_URL, "Bookmarks"),
+               (DOWNLOADS_URL, "Downloads"),
+               (SETTINGS_URL, "Settings"),
+               (KEYS_URL, "Keys"),
+               (LOGS_URL, "Logs")]
+            .iter()
+            .map(|&(url, name)| format!("<a href=\"{}\">{}</a>", url, name))
+            .collect::<Vec<_>>()
+            .join("");
+    format!("<!DOCTYPE html>\n<meta charset=\"utf8\">\n<title>{}</title>\n<style>{}</style>\n\
+             <nav>{}</nav>\n{}",
+            escape(title),
+            STYLE,
+            nav,
+            body)
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// For query values
+fn encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+                 b'A'...b'Z' | b'a'...b'z' | b'0'...b'9' | b'-' | b'_' | b'.' | b'~' => {
+                     (byte as char).to_string()
+                 }
+                 byte => format!("%{:02X}", byte),
+             })
+        .collect()
+}
+
+// "5 minutes ago"
+fn ago(time: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (count, unit) = match now.saturating_sub(time) {
+        secs if secs < 60 => return "just now".to_owned(),
+        secs if secs < 60 * 60 => (secs / 60, "minute"),
+        secs if secs < 24 * 60 * 60 => (secs / 60 / 60, "hour"),
+        secs => (secs / 24 / 60 / 60, "day"),
+    };
+    format!("{} {}{} ago", count, unit, if count > 1 { "s" } else { "" })
+}
+
+// A link to the entry, with its URL below the title
+fn entry_link(entry: &Entry) -> String {
+    format!("<a href=\"{}\">{}</a><br><span class=\"url\">{}</span>",
+            escape(&entry.url),
+            escape(entry.title.as_ref().unwrap_or(&entry.url)),
+            escape(&entry.url))
+}
+
+pub fn about(servo_version: &str, profile: Option<&Path>) -> String {
+    let mut body = String::from("<h1>ServoShell</h1>\n<table>\n");
+    body.push_str(&format!("<tr><td>Version</td><td>{}</td></tr>\n",
+                           env!("CARGO_PKG_VERSION")));
+    body.push_str(&format!("<tr><td>Servo</td><td>{}</td></tr>\n", escape(servo_version)));
+    let profile = match profile {
+        Some(profile) => format!("<code>{}</code>", escape(&profile.display().to_string())),
+        None => "None, private mode".to_owned(),
+    };
+    body.push_str(&format!("<tr><td>Profile</td><td>{}</td></tr>\n</table>\n", profile));
+    body.push_str("<p><a href=\"servoshell://issue/servo\">Report a Servo issue</a></p>\n");
+    body.push_str("<p><a href=\"servoshell://issue/servoshell\">Report a ServoShell issue</a>\
+                   </p>\n");
+    page("About ServoShell", &body)
+}
+
+pub fn history(entries: &[Entry], bookmarks: &[Entry]) -> String {
+    let mut body = String::from("<h1>History</h1>\n");
+    if entries.is_empty() {
+        body.push_str("<p>No history.</p>\n");
+        return page("History", &body);
+    }
+    body.push_str(&format!("<p><a href=\"{}/clear\">Clear history</a></p>\n<table>\n",
+                           HISTORY_URL));
+    for entry in entries.iter().rev() {
+        let bookmark = if bookmarks.iter().any(|b| b.url == entry.url) {
+            String::new()
+        } else {
+            format!("<a href=\"{}/bookmark?url={}\">Bookmark</a>",
+                    HISTORY_URL,
+                    encode(&entry.url))
+        };
+        body.push_str(&format!("<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                               ago(entry.time),
+                               entry_link(entry),
+                               bookmark));
+    }
+    body.push_str("</table>\n");
+    page("History", &body)
+}
+
+pub fn bookmarks(entries: &[Entry]) -> String {
+    let mut body = String::from("<h1>Bookmarks</h1>\n");
+    if entries.is_empty() {
+        body.push_str("<p>No bookmarks. Cmd/Ctrl+D bookmarks the current page.</p>\n");
+        return page("Bookmarks", &body);
+    }
+    body.push_str("<table>\n");
+    for entry in entries {
+        body.push_str(&format!("<tr><td>{}</td><td><a href=\"{}/remove?url={}\">Remove</a>\
+                                </td></tr>\n",
+                               entry_link(entry),
+                               BOOKMARKS_URL,
+                               encode(&entry.url)));
+    }
+    body.push_str("</table>\n");
+    page("Bookmarks", &body)
+}
+
+pub fn downloads(downloads: &[Download]) -> String {
+    let mut body = String::from("<h1>Downloads</h1>\n");
+    if downloads.is_empty() {
+        body.push_str("<p>Nothing downloaded since ServoShell started.</p>\n");
+        return page("Downloads", &body);
+    }
+    body.push_str("<table>\n");
+    for (index, download) in downloads.iter().enumerate().rev() {
+        let status = match download.status {
+            DownloadStatus::InProgress => "Downloading…".to_owned(),
+            DownloadStatus::Done => {
+                format!("<a href=\"{}/open?index={}\">Open</a>", DOWNLOADS_URL, index)
+            }
+            DownloadStatus::Failed(ref error) => format!("Failed: {}", escape(error)),
+        };
+        body.push_str(&format!("<tr><td><code>{}</code><br><span class=\"url\">{}</span></td>\
+                                <td>{}</td></tr>\n",
+                               escape(&download.path.display().to_string()),
+                               escape(download.url.as_str()),
+                               status));
+    }
+    body.push_str("</table>\n");
+    page("Downloads", &body)
+}
+
+fn checkbox(name: &str, label: &str, checked: bool) -> String {
+    format!("<label><input type=\"checkbox\" name=\"{}\"{}> {}</label>\n",
+            name,
+            if checked { " checked" } else { "" },
+            label)
+}
+
+fn selected(selected: bool) -> &'static str {
+    if selected { " selected" } else { "" }
+}
+
+fn text_input(name: &str, label: &str, value: &str) -> String {
+    format!("<label>{}<br><input type=\"text\" name=\"{}\" value=\"{}\"></label>\n",
+            label,
+            name,
+            escape(value))
+}
+
+pub fn settings(prefs: &ShellPreferences, saved: bool) -> String {
+    let mut body = String::from("<h1>Settings</h1>\n");
+    if saved {
+        body.push_str("<p>Settings saved.</p>\n");
+    }
+    body.push_str(&format!("<form action=\"{}/save\">\n<fieldset>\n", SETTINGS_URL));
+    body.push_str(&text_input("home_page",
+                              "Home page (empty for the default one)",
+                              prefs.home_page.as_ref().map_or("", |s| s.as_str())));
+    body.push_str(&text_input("search_engine",
+                              "Search engine (%s is replaced by the search terms)",
+                              &prefs.search_engine));
+    body.push_str(&text_input("default_zoom",
+                              "Default zoom",
+                              &prefs.default_zoom.to_string()));
+    body.push_str(&format!("<label>On startup<br><select name=\"startup\">\
+                            <option value=\"HomePage\"{}>Show the home page</option>\
+                            <option value=\"BlankPage\"{}>Show a blank page</option>\
+                            </select></label>\n",
+                           selected(prefs.startup == StartupBehavior::HomePage),
+                           selected(prefs.startup == StartupBehavior::BlankPage)));
+    body.push_str("</fieldset>\n<fieldset>\n");
+    body.push_str(&text_input("line_height",
+                              "Pixels scrolled per mouse wheel line",
+                              &prefs.line_height.to_string()));
+    body.push_str(&checkbox("diagonal_scrolling",
+                            "Diagonal scrolling",
+                            prefs.diagonal_scrolling));
+    body.push_str(&checkbox("smooth_scrolling", "Smooth scrolling", prefs.smooth_scrolling));
+    body.push_str("</fieldset>\n<fieldset>\n");
+    body.push_str(&checkbox("dark_theme", "Dark theme", prefs.dark_theme));
+    body.push_str(&checkbox("logs_visible", "Show logs", prefs.logs_visible));
+    body.push_str("</fieldset>\n<fieldset>\n");
+    let debug = &prefs.debug_options;
+    body.push_str(&checkbox("show_fragment_borders",
+                            "Show fragment borders",
+                            debug.show_fragment_borders));
+    body.push_str(&checkbox("parallel_display_list_building",
+                            "Parallel display list building",
+                            debug.parallel_display_list_building));
+    body.push_str(&checkbox("show_parallel_layout",
+                            "Show parallel layout",
+                            debug.show_parallel_layout));
+    body.push_str(&checkbox("convert_mouse_to_touch",
+                            "Convert mouse to touch",
+                            debug.convert_mouse_to_touch));
+    body.push_str(&checkbox("show_tiles_borders",
+                            "Show tiles borders",
+                            debug.show_tiles_borders));
+    body.push_str(&checkbox("wr_profiler", "WebRender profiler", debug.wr_profiler));
+    body.push_str(&checkbox("wr_texture_cache_debug",
+                            "WebRender texture cache debug",
+                            debug.wr_texture_cache_debug));
+    body.push_str(&checkbox("wr_render_target_debug",
+                            "WebRender render target debug",
+                            debug.wr_render_target_debug));
+    body.push_str("</fieldset>\n<input type=\"submit\" value=\"Save\">\n</form>\n");
+    body.push_str(&format!("<p><a href=\"{}\">Key bindings</a></p>\n", KEYS_URL));
+    page("Settings", &body)
+}
+
+pub fn keys(keymap: &Keymap) -> String {
+    let mut body = String::from("<h1>Key bindings</h1>\n");
+    body.push_str("<p>Edit <code>keys.json</code> in the profile directory to change them.</p>\n");
+    body.push_str("<table>\n");
+    for (keys, command) in keymap.get_bindings() {
+        let command = serde_json::to_string(command).unwrap_or_default();
+        body.push_str(&format!("<tr><td><code>{}</code></td><td><code>{}</code></td></tr>\n",
+                               escape(&keys),
+                               escape(&command)));
+    }
+    body.push_str("</table>\n");
+    page("Key bindings", &body)
+}
+
+/// What the logs page shows. Built from the page's form submission.
+pub struct LogFilter {
+    /// Hide the logs above this level.
+    pub level: LogLevelFilter,
+    /// Only show this target and its children. Empty for all targets.
+    pub target: String,
+    /// Only show the messages containing this, ignoring case.
+    pub search: String,
+    /// Don't reload the page.
+    pub paused: bool,
+}
+
+impl LogFilter {
+    pub fn from_query(url: &ServoUrl) -> LogFilter {
+        let mut filter = LogFilter {
+            level: LogLevelFilter::Trace,
+            target: String::new(),
+            search: String::new(),
+            paused: false,
+        };
+        for (name, value) in url.as_url().query_pairs() {
+            let value = value.trim();
+            match name.as_ref() {
+                "level" => {
+                    match value.parse() {
+                        Ok(level) => filter.level = level,
+                        Err(_) => warn!("Invalid log level: {}", value),
+                    }
+                }
+                "target" => filter.target = value.to_owned(),
+                "search" => filter.search = value.to_owned(),
+                "paused" => filter.paused = true,
+                name => warn!("Unknown log filter: {}", name),
+            }
+        }
+        filter
+    }
+
+    fn matches(&self, log: &ShellLog) -> bool {
+        log.level <= self.level &&
+        (self.target.is_empty() || log.target == self.target ||
+         log.target.starts_with(&format!("{}::", self.target))) &&
+        (self.search.is_empty() ||
+         log.message.to_lowercase().contains(&self.search.to_lowercase()))
+    }
+}
+
+// HH:MM:SS.mmm, UTC
+fn time_of_day(log: &ShellLog) -> String {
+    let since_epoch = match log.timestamp.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration,
+        Err(_) => return String::new(),
+    };
+    let secs = since_epoch.as_secs() % (24 * 60 * 60);
+    format!("{:02}:{:02}:{:02}.{:03}",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60,
+            since_epoch.subsec_nanos() / 1_000_000)
+}
+
+fn level_options(current: Option<LogLevelFilter>) -> String {
+    let mut options = String::new();
+    for &(level, name) in &[(LogLevelFilter::Error, "Error"),
+                            (LogLevelFilter::Warn, "Warning"),
+                            (LogLevelFilter::Info, "Info"),
+                            (LogLevelFilter::Debug, "Debug"),
+                            (LogLevelFilter::Trace, "Trace")] {
+        options.push_str(&format!("<option value=\"{}\"{}>{}</option>",
+                                  level,
+                                  selected(current == Some(level)),
+                                  name));
+    }
+    options
+}
+
+/// `url` is the URL the page was requested with. It's reloaded to show the
+/// new logs, with the same filter.
+pub fn logs(shell_logs: &ShellLogs, filter: &LogFilter, url: &ServoUrl) -> String {
+    let logs = shell_logs.all_logs();
+    let dropped = shell_logs.dropped();
+    let (record_level, target_levels) = shell_logs.levels();
+    let mut body = format!("<style>{}</style>\n<h1>Logs</h1>\n", LOGS_STYLE);
+
+    // What's recorded. Changing it is an action, only allowed from the page.
+    body.push_str(&format!("<form action=\"{}/record\">\n", LOGS_URL));
+    body.push_str(&format!("<label>Record <select name=\"level\">{}</select></label>\n",
+                           level_options(Some(record_level))));
+    body.push_str(&format!("<label>Keep <input type=\"number\" name=\"capacity\" min=\"0\" \
+                            value=\"{}\"> logs</label>\n",
+                           shell_logs.capacity()));
+    body.push_str("<label>Target <input type=\"text\" name=\"target\"></label>\n");
+    body.push_str(&format!("<label>at <select name=\"target_level\">\
+                            <option value=\"\">Default</option>{}</select></label>\n",
+                           level_options(None)));
+    body.push_str("<input type=\"submit\" value=\"Apply\">\n</form>\n");
+    if !target_levels.is_empty() {
+        let targets: Vec<String> = target_levels
+            .iter()
+            .map(|&(ref target, level)| format!("{}: {}", escape(target), level))
+            .collect();
+        body.push_str(&format!("<p>Target levels: {}</p>\n", targets.join(", ")));
+    }
+
+    // What's shown
+    body.push_str(&format!("<form action=\"{}\">\n", LOGS_URL));
+    body.push_str(&format!("<label>Level <select name=\"level\">{}</select></label>\n",
+                           level_options(Some(filter.level))));
+    body.push_str(&format!("<label>Target <input type=\"text\" name=\"target\" value=\"{}\">\
+                            </label>\n",
+                           escape(&filter.target)));
+    body.push_str(&format!("<label>Search <input type=\"text\" name=\"search\" value=\"{}\">\
+                            </label>\n",
+                           escape(&filter.search)));
+    body.push_str(&checkbox("paused", "Pause", filter.paused));
+    body.push_str("<input type=\"submit\" value=\"Filter\">\n</form>\n");
+    if dropped > 0 {
+        body.push_str(&format!("<p>{} older logs were dropped.</p>\n", dropped));
+    }
+    body.push_str("<table>\n");
+    for log in logs.iter().filter(|log| filter.matches(log)) {
+        body.push_str(&format!("<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+                                </tr>\n",
+                               log.level,
+                               time_of_day(log),
+                               log.level,
+                               escape(&log.target),
+                               escape(&log.message)));
+    }
+    body.push_str("</table>\n");
+    if !filter.paused {
+        // Servo can't load servoshell:// URLs, reloading the file would show
+        // the same logs. Navigating to the URL asks the shell for a new page.
+        let url = serde_json::to_string(url.as_str())
+            .unwrap_or_default()
+            .replace("</", "<\\/");
+        // Reloading would lose what's being typed in the forms, so it waits
+        // while a field has the focus, and stops once a field is edited.
+        body.push_str(&format!("<script>\n\
+                                window.scrollTo(0, document.body.scrollHeight);\n\
+                                var edited = false;\n\
+                                document.addEventListener('input', function() {{\n\
+                                  edited = true;\n\
+                                }});\n\
+                                document.addEventListener('change', function() {{\n\
+                                  edited = true;\n\
+                                }});\n\
+                                function refresh() {{\n\
+                                  var active = document.activeElement;\n\
+                                  var typing = active && (active.tagName == 'INPUT' ||\n\
+                                                          active.tagName == 'SELECT');\n\
+                                  if (edited || typing) {{\n\
+                                    setTimeout(refresh, {1});\n\
+                                  }} else {{\n\
+                                    location.href = {0};\n\
+                                  }}\n\
+                                }}\n\
+                                setTimeout(refresh, {1});\n\
+                                </script>\n",
+                               url,
+                               LOGS_REFRESH_MS));
+    }
+    page("Logs", &body)
+}
+
+/// Change how the logs are recorded, from the logs page submission. An
+/// empty `target_level` removes the target's level.
+pub fn apply_log_settings(url: &ServoUrl, logs: &ShellLogs) {
+    let mut target = None;
+    let mut target_level = None;
+    for (name, value) in url.as_url().query_pairs() {
+        let value = value.trim();
+        match name.as_ref() {
+            "level" => {
+                match value.parse() {
+                    Ok(level) => logs.set_level(level),
+                    Err(_) => warn!("Invalid log level: {}", value),
+                }
+            }
+            "capacity" => {
+                match value.parse() {
+                    Ok(capacity) => logs.set_capacity(capacity),
+                    Err(_) => warn!("Invalid log capacity: {}", value),
+                }
+            }
+            "target" if !value.is_empty() => target = Some(value.to_owned()),
+            "target" => {}
+            "target_level" if value.is_empty() => {}
+            "target_level" => {
+                match value.parse() {
+                    Ok(level) => target_level = Some(level),
+                    Err(_) => warn!("Invalid log level: {}", value),
+                }
+            }
+            name => warn!("Unknown log setting: {}", name),
+        }
+    }
+    if let Some(target) = target {
+        logs.set_target_level(&target, target_level);
+    }
+}
+
+/// Build preferences from the settings form submission. Unchecked
+/// checkboxes are not part of the query.
+pub fn settings_from_query(url: &ServoUrl, current: &ShellPreferences) -> ShellPreferences {
+    let mut prefs = current.clone();
+    prefs.dark_theme = false;
+    prefs.logs_visible = false;
+    prefs.diagonal_scrolling = false;
+    prefs.smooth_scrolling = false;
+    {
+        let debug = &mut prefs.debug_options;
+        debug.show_fragment_borders = false;
+        debug.parallel_display_list_building = false;
+        debug.show_parallel_layout = false;
+        debug.convert_mouse_to_touch = false;
+        debug.show_tiles_borders = false;
+        debug.wr_profiler = false;
+        debug.wr_texture_cache_debug = false;
+        debug.wr_render_target_debug = false;
+    }
+    for (name, value) in url.as_url().query_pairs() {
+        let value = value.trim();
+        match name.as_ref() {
+            "home_page" => {
+                if value.is_empty() {
+                    prefs.home_page = None;
+                } else {
+                    match cli::parse_url(value) {
+                        Some(url) => prefs.home_page = Some(url),
+                        None => warn!("Invalid home page URL: {}", value),
+                    }
+                }
+            }
+            "search_engine" => {
+                if value.contains("%s") {
+                    prefs.search_engine = value.to_owned();
+                } else {
+                    warn!("Search engine URL must contain %s: {}", value);
+                }
+            }
+            "default_zoom" => {
+                match value.parse::<f32>() {
+                    Ok(zoom) if zoom > 0.0 => prefs.default_zoom = zoom,
+                    _ => warn!("Invalid zoom value: {}", value),
+                }
+            }
+            "line_height" => {
+                match value.parse::<f32>() {
+                    Ok(height) if height > 0.0 => prefs.line_height = height,
+                    _ => warn!("Invalid line height: {}", value),
+                }
+            }
+            "diagonal_scrolling" => prefs.diagonal_scrolling = true,
+            "smooth_scrolling" => prefs.smooth_scrolling = true,
+            "startup" if value == "BlankPage" => prefs.startup = StartupBehavior::BlankPage,
+            "startup" => prefs.startup = StartupBehavior::HomePage,
+            "dark_theme" => prefs.dark_theme = true,
+            "logs_visible" => prefs.logs_visible = true,
+            "show_fragment_borders" => prefs.debug_options.show_fragment_borders = true,
+            "parallel_display_list_building" => {
+                prefs.debug_options.parallel_display_list_building = true
+            }
+            "show_parallel_layout" => prefs.debug_options.show_parallel_layout = true,
+            "convert_mouse_to_touch" => prefs.debug_options.convert_mouse_to_touch = true,
+            "show_tiles_borders" => prefs.debug_options.show_tiles_borders = true,
+            "wr_profiler" => prefs.debug_options.wr_profiler = true,
+            "wr_texture_cache_debug" => prefs.debug_options.wr_texture_cache_debug = true,
+            "wr_render_target_debug" => prefs.debug_options.wr_render_target_debug = true,
+            name => warn!("Unknown setting: {}", name),
+        }
+    }
+    prefs
+}
@@ -31,8 +31,11 @@ fn register() {
         utils::get_event_queue(this).push(AppEvent::DidChangeScreenParameters)
     }
 
-    extern "C" fn will_terminate(this: &Object, _sel: Sel, _notification: id) {
-        utils::get_event_queue(this).push(AppEvent::WillTerminate)
+    // Terminating exits without returning to the event loop. Cancel it, the
+    // shell saves what's pending and exits.
+    extern "C" fn should_terminate(this: &Object, _sel: Sel, _sender: id) -> NSUInteger {
+        utils::get_event_queue(this).push(AppEvent::WillTerminate);
+        0 // NSTerminateCancel
     }
 
     extern "C" fn validate_ui(_this: &Object, _sel: Sel, item: id) -> BOOL {
@@ -63,8 +66,8 @@ fn register() {
                          did_finish_launching as extern "C" fn(&Object, Sel, id));
         class.add_method(sel!(applicationDidChangeScreenParameter:),
                          did_change_screen_parameter as extern "C" fn(&Object, Sel, id));
-        class.add_method(sel!(applicationWillTerminate:),
-                         will_terminate as extern "C" fn(&Object, Sel, id));
+        class.add_method(sel!(applicationShouldTerminate:),
+                         should_terminate as extern "C" fn(&Object, Sel, id) -> NSUInteger);
 
         class.add_method(sel!(validateUserInterfaceItem:),
                          validate_ui as extern "C" fn(&Object, Sel, id) -> BOOL);
@@ -335,4 +338,27 @@ impl AppMethods for App {
 
         Ok(Box::new(window::Window::new(state, nswindow, nspopover)))
     }
+
+    fn set_clipboard(&self, text: &str) -> Result<(), String> {
+        unsafe {
+            let pasteboard: id = msg_send![class("NSPasteboard"), generalPasteboard];
+            msg_send![pasteboard, clearContents];
+            let string = NSString::alloc(nil).init_str(text);
+            let done: BOOL = msg_send![pasteboard,
+                                       setString:string
+                                       forType:NSPasteboardTypeString];
+            // The pasteboard keeps its own copy
+            msg_send![string, release];
+            if done == YES {
+                Ok(())
+            } else {
+                Err("Can't write to the pasteboard".to_owned())
+            }
+        }
+    }
+}
+
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {
+    static NSPasteboardTypeString: id;
 }
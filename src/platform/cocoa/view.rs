@@ -77,9 +77,19 @@ pub fn register() {
                         appkit::NSEventPhaseEnded => TouchPhase::Ended,
                         _ => TouchPhase::Moved,
                     };
-                    let event = ViewEvent::MouseWheel(delta, phase);
+                    let (x, y) = cursor_coordinates_in_view(this, nsevent);
+                    let event = ViewEvent::MouseWheel(delta, phase, x, y);
+                    utils::get_event_queue(this).push(event);
+                }
+                NSEventTypeMagnify => {
+                    let magnification: CGFloat = msg_send![nsevent, magnification];
+                    let event = ViewEvent::Magnify(1.0 + magnification as f32);
                     utils::get_event_queue(this).push(event);
                 }
+                NSEventTypeSmartMagnify => {
+                    let (x, y) = cursor_coordinates_in_view(this, nsevent);
+                    utils::get_event_queue(this).push(ViewEvent::SmartZoom(x, y));
+                }
                 NSMouseMoved => {
                     let (x, y) = cursor_coordinates_in_view(this, nsevent);
                     let event = ViewEvent::MouseMoved(x, y);
@@ -134,6 +144,22 @@ pub fn register() {
         }
     }
 
+    // Edit menu items. Servo implements them as key bindings.
+    extern "C" fn edit_command(this: &Object, sel: Sel, _sender: id) {
+        let (c, key) = if sel == sel!(copy:) {
+            ('c', Key::C)
+        } else if sel == sel!(cut:) {
+            ('x', Key::X)
+        } else if sel == sel!(paste:) {
+            ('v', Key::V)
+        } else {
+            ('a', Key::A)
+        };
+        let queue = utils::get_event_queue(this);
+        queue.push(ViewEvent::KeyEvent(Some(c), key, KeyState::Pressed, KeyModifiers::SUPER));
+        queue.push(ViewEvent::KeyEvent(None, key, KeyState::Released, KeyModifiers::SUPER));
+    }
+
     extern "C" fn awake_from_nib(this: &mut Object, _sel: Sel) {
         let event_queue: Vec<ViewEvent> = Vec::new();
         let event_queue_ptr = Box::into_raw(Box::new(event_queue));
@@ -167,6 +193,18 @@ pub fn register() {
                          store_nsevent as extern "C" fn(&Object, Sel, id));
         class.add_method(sel!(mouseUp:),
                          store_nsevent as extern "C" fn(&Object, Sel, id));
+        class.add_method(sel!(rightMouseDown:),
+                         store_nsevent as extern "C" fn(&Object, Sel, id));
+        class.add_method(sel!(rightMouseUp:),
+                         store_nsevent as extern "C" fn(&Object, Sel, id));
+        class.add_method(sel!(otherMouseDown:),
+                         store_nsevent as extern "C" fn(&Object, Sel, id));
+        class.add_method(sel!(otherMouseUp:),
+                         store_nsevent as extern "C" fn(&Object, Sel, id));
+        class.add_method(sel!(magnifyWithEvent:),
+                         store_nsevent as extern "C" fn(&Object, Sel, id));
+        class.add_method(sel!(smartMagnifyWithEvent:),
+                         store_nsevent as extern "C" fn(&Object, Sel, id));
         class.add_method(sel!(mouseMoved:),
                          store_nsevent as extern "C" fn(&Object, Sel, id));
         class.add_method(sel!(keyDown:),
@@ -174,6 +212,11 @@ pub fn register() {
         class.add_method(sel!(keyUp:),
                          store_nsevent as extern "C" fn(&Object, Sel, id));
 
+        class.add_method(sel!(copy:), edit_command as extern "C" fn(&Object, Sel, id));
+        class.add_method(sel!(cut:), edit_command as extern "C" fn(&Object, Sel, id));
+        class.add_method(sel!(paste:), edit_command as extern "C" fn(&Object, Sel, id));
+        class.add_method(sel!(selectAll:), edit_command as extern "C" fn(&Object, Sel, id));
+
         class.add_method(sel!(acceptsFirstResponder),
                          accept_first_responder as extern "C" fn(&Object, Sel) -> BOOL);
 
@@ -305,34 +348,33 @@ impl ViewMethods for View {
             let left = 0;
             let right = 0;
 
+            // Cocoa coordinates start from the bottom of the screen
+            let screen: id = msg_send![nswindow, screen];
+            let screen_frame: NSRect = msg_send![screen, frame];
+            let avail_frame: NSRect = msg_send![screen, visibleFrame];
+            let window_frame: NSRect = msg_send![nswindow, frame];
+            let flip = |rect: NSRect| {
+                (rect.origin.x as i32,
+                 (screen_frame.size.height - rect.origin.y - rect.size.height) as i32)
+            };
+
             DrawableGeometry {
                 view_size: (view_frame.size.width as u32, view_frame.size.height as u32),
                 margins: (top, right, bottom, left),
-                position: (0, 0),
+                position: flip(window_frame),
                 hidpi_factor: hidpi_factor as f32,
+                screen_size: (screen_frame.size.width as u32, screen_frame.size.height as u32),
+                screen_avail_position: flip(avail_frame),
+                screen_avail_size: (avail_frame.size.width as u32,
+                                    avail_frame.size.height as u32),
             }
         }
     }
 
     fn get_events(&self) -> Vec<ViewEvent> {
-        // FIXME: we should allow only one GeometryDidChange
         let nsobject = unsafe { &*self.nsview };
         utils::get_event_queue(nsobject).drain(..).collect()
     }
-
-    // FIXME: should be controlled by state
-    fn enter_fullscreen(&self) {
-        unsafe {
-            msg_send![self.nsview, enterFullScreenMode:nil withOptions:nil];
-        }
-    }
-
-    // FIXME: should be controlled by state
-    fn exit_fullscreen(&self) {
-        unsafe {
-            msg_send![self.nsview, exitFullScreenModeWithOptions: nil];
-        }
-    }
 }
 
 
@@ -11,10 +11,11 @@ use objc::declare::ClassDecl;
 use objc::runtime::{Class, Object, Sel};
 use platform::View;
 use servo::EventLoopWaker;
-use state::{BrowserState, ChangeType, DiffKey, WindowState};
+use state::{BrowserState, ChangeType, ContextMenuItem, DiffKey, WindowState};
 use std::f64;
 use std::ffi::CStr;
 use std::os::raw::c_void;
+use std::path::PathBuf;
 use std::rc::Rc;
 use super::utils::{self, get_win_state, get_app_state};
 use traits::view::ViewMethods;
@@ -25,6 +26,15 @@ use traits::window::{WindowEvent, WindowCommand, WindowMethods};
 #[link_args = "-rpath target/MMTabBarView/Release/ -rpath @executable_path/../Frameworks/"]
 extern "C" {}
 
+// NSFullScreenWindowMask
+const FULL_SCREEN_WINDOW_MASK: NSUInteger = 1 << 14;
+
+// Position, screen, or scale factor changes
+const GEOMETRY_NOTIFICATIONS: [&'static str; 3] =
+    ["NSWindowDidMoveNotification",
+     "NSWindowDidChangeScreenNotification",
+     "NSWindowDidChangeBackingPropertiesNotification"];
+
 pub fn register() {
 
     /* NSWindow subclass */
@@ -92,6 +102,10 @@ pub fn register() {
                     Some(WindowEvent::DidEnterFullScreen)
                 } else if NSString::isEqualToString(name, "NSWindowDidExitFullScreenNotification") {
                     Some(WindowEvent::DidExitFullScreen)
+                } else if GEOMETRY_NOTIFICATIONS
+                              .iter()
+                              .any(|n| NSString::isEqualToString(name, n)) {
+                    Some(WindowEvent::GeometryDidChange)
                 } else if NSString::isEqualToString(name, "NSWindowWillCloseNotification") {
                     Some(WindowEvent::WillClose)
                 } else if NSString::isEqualToString(name, "NSPopoverWillCloseNotification") {
@@ -274,6 +288,20 @@ pub fn register() {
             utils::get_event_queue(this).push(WindowEvent::DoCommand(cmd));
         }
 
+        extern "C" fn context_menu_item(this: &Object, _sel: Sel, item: id) {
+            let tag: NSInteger = unsafe { msg_send![item, tag] };
+            let cmd = match get_win_state().context_menu {
+                Some(ref menu) => {
+                    match menu.items.get(tag as usize) {
+                        Some(&ContextMenuItem::Entry { ref command, .. }) => command.clone(),
+                        _ => return,
+                    }
+                }
+                None => return,
+            };
+            utils::get_event_queue(this).push(WindowEvent::DoCommand(cmd));
+        }
+
         extern "C" fn tabview_selected(this: &Object, _sel: Sel, tabview: id, item: id) {
             unsafe {
                 let rendering: bool = *this.get_ivar("rendering");
@@ -293,6 +321,12 @@ pub fn register() {
                              record_notification as extern "C" fn(&Object, Sel, id));
             class.add_method(sel!(windowDidExitFullScreen:),
                              record_notification as extern "C" fn(&Object, Sel, id));
+            class.add_method(sel!(windowDidChangeScreen:),
+                             record_notification as extern "C" fn(&Object, Sel, id));
+            class.add_method(sel!(windowDidMove:),
+                             record_notification as extern "C" fn(&Object, Sel, id));
+            class.add_method(sel!(windowDidChangeBackingProperties:),
+                             record_notification as extern "C" fn(&Object, Sel, id));
             class.add_method(sel!(windowWillClose:),
                              record_notification as extern "C" fn(&Object, Sel, id));
             class.add_method(sel!(popoverWillClose:),
@@ -362,6 +396,8 @@ pub fn register() {
 
             class.add_method(sel!(shellSubmitUserInput:),
                              submit_user_input as extern "C" fn(&Object, Sel, id));
+            class.add_method(sel!(shellContextMenuItem:),
+                             context_menu_item as extern "C" fn(&Object, Sel, id));
 
             class.add_method(sel!(tabView:didSelectTabViewItem:),
                              tabview_selected as extern "C" fn(&Object, Sel, id, id));
@@ -593,6 +629,16 @@ impl Window {
         }
     }
 
+    fn render_fullscreen(&self, state: &WindowState) {
+        unsafe {
+            let mask: NSUInteger = msg_send![self.nswindow, styleMask];
+            let is_fullscreen = mask & FULL_SCREEN_WINDOW_MASK != 0;
+            if is_fullscreen != state.fullscreen {
+                msg_send![self.nswindow, toggleFullScreen: nil];
+            }
+        }
+    }
+
     fn render_focus(&self, state: &BrowserState) {
         if state.urlbar_focused {
             let field = utils::get_view_by_id(self.nswindow, "shellToolbarViewUrlbarTextfield")
@@ -601,7 +647,11 @@ impl Window {
                 msg_send![field, becomeFirstResponder];
             }
         } else {
-            // FIXME: unfocus urlbar and focus browser
+            let view = utils::get_view_by_id(self.nswindow, "shellViewServo")
+                .expect("Can't find shellViewServo");
+            unsafe {
+                msg_send![self.nswindow, makeFirstResponder: view];
+            }
         }
     }
 
@@ -632,6 +682,47 @@ impl Window {
         }
     }
 
+    fn render_context_menu(&self, state: &WindowState) {
+        let menu = match state.context_menu {
+            Some(ref menu) => menu,
+            None => return,
+        };
+        unsafe {
+            let delegate: id = msg_send![self.nswindow, delegate];
+            let nsmenu: id = msg_send![class("NSMenu"), alloc];
+            let nsmenu: id = msg_send![nsmenu, initWithTitle: NSString::alloc(nil).init_str("")];
+            // Enabled state comes from the model, not from validateAction
+            msg_send![nsmenu, setAutoenablesItems: NO];
+            for (i, item) in menu.items.iter().enumerate() {
+                let nsitem: id = match *item {
+                    ContextMenuItem::Separator => msg_send![class("NSMenuItem"), separatorItem],
+                    ContextMenuItem::Entry { ref label, enabled, .. } => {
+                        let title = NSString::alloc(nil).init_str(label);
+                        let key = NSString::alloc(nil).init_str("");
+                        let nsitem: id = msg_send![class("NSMenuItem"), alloc];
+                        let nsitem: id = msg_send![nsitem,
+                                                   initWithTitle:title
+                                                   action:sel!(shellContextMenuItem:)
+                                                   keyEquivalent:key];
+                        msg_send![nsitem, setTarget: delegate];
+                        msg_send![nsitem, setTag: i as NSInteger];
+                        msg_send![nsitem, setEnabled: if enabled { YES } else { NO }];
+                        msg_send![nsitem, autorelease];
+                        nsitem
+                    }
+                };
+                msg_send![nsmenu, addItem: nsitem];
+            }
+            // Blocks until the menu is closed. The selected item, if any, has
+            // already queued its command.
+            let location: NSPoint = msg_send![class("NSEvent"), mouseLocation];
+            msg_send![nsmenu, popUpMenuPositioningItem:nil atLocation:location inView:nil];
+            msg_send![nsmenu, release];
+        }
+        let nsobject = unsafe { &*self.nswindow };
+        utils::get_event_queue(nsobject).push(WindowEvent::ContextMenuClosed);
+    }
+
     fn render_logs(&self, state: &WindowState) {
         let logs = utils::get_view_by_id(self.nswindow, "shellViewLogs")
             .expect("Can't find shellViewLogs view");
@@ -746,6 +837,8 @@ impl WindowMethods for Window {
 
         // FIXME: Most of these render functions have overlap logic with the validate_action
 
+        let mut context_menu_changed = false;
+
         for change in diff {
             use self::DiffKey as K;
             match change {
@@ -780,7 +873,7 @@ impl WindowMethods for Window {
                                 }
                                 K::zoom => self.render_zoom_buttons(current_browser_state),
                                 K::urlbar_focused => self.render_focus(current_browser_state),
-                                K::user_input => {
+                                K::user_input | K::pinch_zoom => {
                                     // Nothing to do
                                 }
                                 _ => {
@@ -800,6 +893,12 @@ impl WindowMethods for Window {
                         &[K::options_open] => {
                             self.render_popover(state);
                         }
+                        &[K::fullscreen] => {
+                            self.render_fullscreen(state);
+                        }
+                        &[K::context_menu, _..] => {
+                            context_menu_changed = true;
+                        }
                         &[K::status] => {
                             self.render_status(state);
                         }
@@ -813,6 +912,7 @@ impl WindowMethods for Window {
                         &[K::tabs, K::Index(i), K::Alive, K::can_go_forward] |
                         &[K::tabs, K::Index(i), K::Alive, K::can_go_back] |
                         &[K::tabs, K::Index(i), K::Alive, K::url] |
+                        &[K::tabs, K::Index(i), K::Alive, K::pinch_zoom] |
                         &[K::tabs, K::Index(i), K::Alive, K::is_loading] if i != idx => {
                             // Nothing to do
                         }
@@ -839,6 +939,9 @@ impl WindowMethods for Window {
                                 self.render_default_webbrowser_button(browser);
                             }
                         }
+                        &[K::context_menu, _..] => {
+                            context_menu_changed = true;
+                        }
                         _ => println!("Window::render: unexpected Added keys: {:?}", keys),
                     }
                 }
@@ -847,6 +950,9 @@ impl WindowMethods for Window {
                         &[K::tabs, K::Index(i), K::Alive] => {
                             self.render_remove_tab(state, i);
                         }
+                        &[K::context_menu, _..] => {
+                            context_menu_changed = true;
+                        }
                         _ => println!("Window::render: unexpected Removed keys: {:?}", keys),
                     }
                 }
@@ -856,6 +962,10 @@ impl WindowMethods for Window {
         // FIXME
         self.update_theme();
 
+        if context_menu_changed {
+            self.render_context_menu(state);
+        }
+
         unsafe {
             (*delegate).set_ivar("rendering", false);
         }
@@ -885,6 +995,40 @@ impl WindowMethods for Window {
         }
     }
 
+    fn set_inner_size(&self, width: u32, height: u32) {
+        unsafe {
+            let size = NSSize::new(width as f64, height as f64);
+            msg_send![self.nswindow, setContentSize: size];
+        }
+    }
+
+    fn choose_save_path(&self, default_name: &str) -> Option<PathBuf> {
+        unsafe {
+            let panel: id = msg_send![class("NSSavePanel"), savePanel];
+            let name = NSString::alloc(nil).init_str(default_name);
+            msg_send![panel, setNameFieldStringValue: name];
+            let response: NSInteger = msg_send![panel, runModal];
+            // NSModalResponseOK
+            if response != 1 {
+                return None;
+            }
+            let url: id = msg_send![panel, URL];
+            let path: id = msg_send![url, path];
+            let path = CStr::from_ptr(NSString::UTF8String(path));
+            Some(PathBuf::from(path.to_string_lossy().into_owned()))
+        }
+    }
+
+    fn set_position(&self, x: i32, y: i32) {
+        // Cocoa coordinates start from the bottom of the screen
+        unsafe {
+            let screen: id = msg_send![self.nswindow, screen];
+            let frame: NSRect = msg_send![screen, frame];
+            let point = NSPoint::new(x as f64, frame.size.height - y as f64);
+            msg_send![self.nswindow, setFrameTopLeftPoint: point];
+        }
+    }
+
     fn get_events(&self) -> Vec<WindowEvent> {
         let nsobject = unsafe { &*self.nswindow };
         utils::get_event_queue(nsobject).drain(..).collect()
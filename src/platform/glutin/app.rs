@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use clipboard::{ClipboardContext, ClipboardProvider};
 use glutin::{self, GlContext};
 use platform::Window;
 use servo::{ServoCursor, EventLoopWaker};
@@ -116,6 +117,9 @@ impl AppMethods for App {
                 ChangeType::Modified(keys) => {
                     match keys.as_slice() {
                         &[K::cursor] => self.render_cursor(state.cursor),
+                        &[K::dark_theme] => {
+                            // Nothing to do
+                        }
                         _ => println!("App::render: unexpected keys: {:?}", keys),
                     }
                 }
@@ -130,19 +134,19 @@ impl AppMethods for App {
 
     fn new_window<'a>(&self, state: &WindowState) -> Result<Box<WindowMethods>, &'a str> {
 
-        #[cfg(target_os = "windows")]
-        let factor = utils::windows_hidpi_factor();
-        #[cfg(not(target_os = "windows"))]
-        let factor = 1.0;
-
-        let window = glutin::WindowBuilder::new().with_dimensions(1024 * factor as u32,
-                                                                  768 * factor as u32);
+        let window = glutin::WindowBuilder::new().with_dimensions(1024, 768);
         let context = glutin::ContextBuilder::new()
             .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 2)))
             .with_vsync(true);
         let glutin_window = glutin::GlWindow::new(window, context, &*self.event_loop.borrow_mut())
             .unwrap();
 
+        // 1024x768 device independent pixels
+        let factor = utils::dip_factor(&glutin_window);
+        if factor != 1.0 {
+            glutin_window.set_inner_size((1024.0 * factor) as u32, (768.0 * factor) as u32);
+        }
+
         let gl = unsafe {
             glutin_window
                 .context()
@@ -158,6 +162,10 @@ impl AppMethods for App {
         glutin_window.show();
 
         let id = glutin_window.id();
+        let screen_size = self.event_loop
+            .borrow()
+            .get_primary_monitor()
+            .get_dimensions();
 
         self.windows
             .borrow_mut()
@@ -171,6 +179,7 @@ impl AppMethods for App {
                         view_events: vec![],
                         window_events: vec![],
                         mouse_coordinate: (0, 0),
+                        screen_size,
                     });
 
         Ok(Box::new(Window::new(id, state, self.windows.clone())))
@@ -191,24 +200,10 @@ impl AppMethods for App {
                         let mut windows = self.windows.borrow_mut();
                         match windows.get_mut(&window_id) {
                             Some(window) => {
-                                match (*window).glutin_event_to_command(&event) {
-                                    Some(command) => {
-                                        window
-                                            .window_events
-                                            .push(WindowEvent::DoCommand(command));
-                                        call_callback = true;
-                                    }
-                                    None => {
-                                        match (*window).glutin_event_to_view_event(&event) {
-                                            Some(event) => {
-                                                window.view_events.push(event);
-                                                call_callback = true;
-                                            }
-                                            None => {
-                                                warn!("Got unknown glutin event: {:?}", event);
-                                            }
-                                        }
-                                    }
+                                if window.handle_glutin_event(&event) {
+                                    call_callback = true;
+                                } else {
+                                    warn!("Got unknown glutin event: {:?}", event);
                                 }
                             }
                             None => {
@@ -234,4 +229,17 @@ impl AppMethods for App {
             });
         callback()
     }
+
+    // The clipboard crate only speaks X11. Without XWayland, the text
+    // goes through wl-copy.
+    fn set_clipboard(&self, text: &str) -> Result<(), String> {
+        ClipboardContext::new()
+            .and_then(|mut ctx| ctx.set_contents(text.to_owned()))
+            .map_err(|e| e.to_string())
+            .or_else(|e| if env::var_os("WAYLAND_DISPLAY").is_some() {
+                         utils::wayland_set_clipboard(text)
+                     } else {
+                         Err(e)
+                     })
+    }
 }
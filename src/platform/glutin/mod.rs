@@ -25,90 +25,51 @@ pub struct GlutinWindow {
     key_modifiers: Cell<KeyModifiers>,
     last_pressed_key: Cell<Option<Key>>,
     mouse_coordinate: (i32, i32),
+    // FIXME: glutin doesn't tell which monitor the window is on, and the
+    // event loop can't be queried while it runs. This is the primary
+    // monitor when the window was created.
+    screen_size: (u32, u32),
     view_events: Vec<ViewEvent>,
     window_events: Vec<WindowEvent>,
 }
 
 impl GlutinWindow {
-    pub fn glutin_event_to_command(&self, event: &glutin::WindowEvent) -> Option<WindowCommand> {
+    /// Queue the window and view events matching a glutin event. Returns
+    /// false if the event is not supported.
+    pub fn handle_glutin_event(&mut self, event: &glutin::WindowEvent) -> bool {
         match *event {
-            glutin::WindowEvent::KeyboardInput {
-                input: glutin::KeyboardInput {
-                    state: glutin::ElementState::Pressed,
-                    virtual_keycode,
-                    modifiers,
-                    ..
-                },
+            glutin::WindowEvent::MouseInput {
+                state,
+                button: glutin::MouseButton::Other(button),
                 ..
             } => {
-                match (virtual_keycode,
-                       utils::cmd_or_ctrl(modifiers),
-                       modifiers.ctrl,
-                       modifiers.shift) {
-                    (Some(glutin::VirtualKeyCode::R), true, _, _) => Some(WindowCommand::Reload),
-                    (Some(glutin::VirtualKeyCode::Left), true, _, _) => {
-                        Some(WindowCommand::NavigateBack)
-                    }
-                    (Some(glutin::VirtualKeyCode::Right), true, _, _) => {
-                        Some(WindowCommand::NavigateForward)
-                    }
-                    (Some(glutin::VirtualKeyCode::L), true, _, _) => {
-                        Some(WindowCommand::OpenLocation)
-                    }
-                    (Some(glutin::VirtualKeyCode::Equals), true, _, _) => {
-                        Some(WindowCommand::ZoomIn)
-                    }
-                    (Some(glutin::VirtualKeyCode::Minus), true, _, _) => {
-                        Some(WindowCommand::ZoomOut)
-                    }
-                    (Some(glutin::VirtualKeyCode::Key0), true, _, _) => {
-                        Some(WindowCommand::ZoomToActualSize)
-                    }
-                    (Some(glutin::VirtualKeyCode::T), true, _, _) => Some(WindowCommand::NewTab),
-                    (Some(glutin::VirtualKeyCode::W), true, _, _) => Some(WindowCommand::CloseTab),
-                    (Some(glutin::VirtualKeyCode::Tab), _, true, false) => {
-                        Some(WindowCommand::NextTab)
-                    }
-                    (Some(glutin::VirtualKeyCode::Tab), _, true, true) => {
-                        Some(WindowCommand::PrevTab)
-                    }
-                    (Some(glutin::VirtualKeyCode::Key1), true, _, _) => {
-                        Some(WindowCommand::SelectTab(0))
-                    }
-                    (Some(glutin::VirtualKeyCode::Key2), true, _, _) => {
-                        Some(WindowCommand::SelectTab(1))
-                    }
-                    (Some(glutin::VirtualKeyCode::Key3), true, _, _) => {
-                        Some(WindowCommand::SelectTab(2))
-                    }
-                    (Some(glutin::VirtualKeyCode::Key4), true, _, _) => {
-                        Some(WindowCommand::SelectTab(3))
-                    }
-                    (Some(glutin::VirtualKeyCode::Key5), true, _, _) => {
-                        Some(WindowCommand::SelectTab(4))
-                    }
-                    (Some(glutin::VirtualKeyCode::Key6), true, _, _) => {
-                        Some(WindowCommand::SelectTab(5))
-                    }
-                    (Some(glutin::VirtualKeyCode::Key7), true, _, _) => {
-                        Some(WindowCommand::SelectTab(6))
-                    }
-                    (Some(glutin::VirtualKeyCode::Key8), true, _, _) => {
-                        Some(WindowCommand::SelectTab(7))
-                    }
-                    (Some(glutin::VirtualKeyCode::Key9), true, _, _) => {
-                        Some(WindowCommand::SelectTab(8))
+                // Extra buttons, as numbered by X11
+                let command = match button {
+                    8 => WindowCommand::NavigateBack,
+                    9 => WindowCommand::NavigateForward,
+                    _ => return false,
+                };
+                if state == glutin::ElementState::Pressed {
+                    self.window_events.push(WindowEvent::DoCommand(command));
+                }
+                true
+            }
+            _ => {
+                match self.glutin_event_to_view_event(event) {
+                    Some(event) => {
+                        self.view_events.push(event);
+                        true
                     }
-                    _ => None,
+                    None => false,
                 }
             }
-            _ => None,
         }
     }
 
-    pub fn glutin_event_to_view_event(&mut self, event: &glutin::WindowEvent) -> Option<ViewEvent> {
+    fn glutin_event_to_view_event(&mut self, event: &glutin::WindowEvent) -> Option<ViewEvent> {
         match *event {
-            glutin::WindowEvent::Resized(..) => Some(ViewEvent::GeometryDidChange),
+            glutin::WindowEvent::Resized(..) |
+            glutin::WindowEvent::Moved(..) => Some(ViewEvent::GeometryDidChange),
             glutin::WindowEvent::MouseMoved { position: (x, y), .. } => {
                 self.mouse_coordinate = (x as i32, y as i32);
                 Some(ViewEvent::MouseMoved(x as i32, y as i32))
@@ -127,46 +88,51 @@ impl GlutinWindow {
                     glutin::TouchPhase::Started => TouchPhase::Started,
                     glutin::TouchPhase::Moved => TouchPhase::Moved,
                     glutin::TouchPhase::Ended => TouchPhase::Ended,
-                    // FIXME:
-                    glutin::TouchPhase::Cancelled => TouchPhase::Ended,
+                    glutin::TouchPhase::Cancelled => TouchPhase::Cancelled,
                 };
-                Some(ViewEvent::MouseWheel(delta, phase))
+                Some(ViewEvent::MouseWheel(delta,
+                                           phase,
+                                           self.mouse_coordinate.0,
+                                           self.mouse_coordinate.1))
             }
-            glutin::WindowEvent::MouseInput {
-                state,
-                button: glutin::MouseButton::Left,
-                ..
-            } => {
+            glutin::WindowEvent::MouseInput { state, button, .. } => {
                 let state = match state {
                     glutin::ElementState::Released => ElementState::Released,
                     glutin::ElementState::Pressed => ElementState::Pressed,
                 };
+                let button = match button {
+                    glutin::MouseButton::Left => MouseButton::Left,
+                    glutin::MouseButton::Right => MouseButton::Right,
+                    glutin::MouseButton::Middle => MouseButton::Middle,
+                    glutin::MouseButton::Other(_) => return None,
+                };
                 Some(ViewEvent::MouseInput(state,
-                                           MouseButton::Left,
+                                           button,
                                            self.mouse_coordinate.0,
                                            self.mouse_coordinate.1))
             }
+            glutin::WindowEvent::Touch(glutin::Touch {
+                                           phase,
+                                           location: (x, y),
+                                           id,
+                                           ..
+                                       }) => {
+                let phase = match phase {
+                    glutin::TouchPhase::Started => TouchPhase::Started,
+                    glutin::TouchPhase::Moved => TouchPhase::Moved,
+                    glutin::TouchPhase::Ended => TouchPhase::Ended,
+                    glutin::TouchPhase::Cancelled => TouchPhase::Cancelled,
+                };
+                Some(ViewEvent::Touch(id as i32, phase, x as i32, y as i32))
+            }
             glutin::WindowEvent::ReceivedCharacter(ch) => {
-
+                // FIXME: glutin doesn't report composition (preedit) events,
+                // and can't place the IME candidate window. Dead keys, compose
+                // sequences and input methods only give us the committed
+                // characters, after the key that committed them.
+                let key = utils::received_character_key(ch, self.last_pressed_key.get());
                 let mods = self.key_modifiers.get();
-
-                // FIXME: cleanup
-                let event = if let Some(last_pressed_key) = self.last_pressed_key.get() {
-                    Some(ViewEvent::KeyEvent(Some(ch), last_pressed_key, KeyState::Pressed, mods))
-                } else {
-                    if !ch.is_control() {
-                        match utils::char_to_script_key(ch) {
-                            Some(key) => {
-                                Some(ViewEvent::KeyEvent(Some(ch), key, KeyState::Pressed, mods))
-                            }
-                            None => None,
-                        }
-                    } else {
-                        None
-                    }
-                };
-                self.last_pressed_key.set(None);
-                event
+                key.map(|key| ViewEvent::KeyEvent(Some(ch), key, KeyState::Pressed, mods))
             }
             glutin::WindowEvent::KeyboardInput {
                 input: glutin::KeyboardInput {
@@ -200,9 +166,8 @@ impl GlutinWindow {
                         glutin::ElementState::Released => KeyState::Released,
                     };
                     if state == KeyState::Pressed {
-                        if utils::is_printable(virtual_keycode) {
-                            self.last_pressed_key.set(Some(key));
-                        }
+                        let key = utils::pressed_key_for_characters(key, virtual_keycode);
+                        self.last_pressed_key.set(key);
                     }
                     Some(ViewEvent::KeyEvent(None, key, state, self.key_modifiers.get()))
                 } else {
@@ -281,6 +281,208 @@ pub fn char_to_script_key(c: char) -> Option<Key> {
     }
 }
 
+/// The key to remember after a key press, for the characters it commits.
+pub fn pressed_key_for_characters(key: Key, key_code: glutin::VirtualKeyCode) -> Option<Key> {
+    if is_printable(key_code) {
+        Some(key)
+    } else {
+        None
+    }
+}
+
+/// The key to report with a received character. Dead keys, compose
+/// sequences and input methods commit their characters after the last key
+/// press, so all of them are reported with that key.
+pub fn received_character_key(ch: char, last_pressed_key: Option<Key>) -> Option<Key> {
+    if ch.is_control() && last_pressed_key.is_none() {
+        return None;
+    }
+    last_pressed_key.or_else(|| char_to_script_key(ch))
+}
+
+// Xlib is loaded once, on the event loop thread
+#[cfg(target_os = "linux")]
+fn with_xlib<T, F>(f: F) -> Option<T>
+    where F: FnOnce(&::x11_dl::xlib::Xlib) -> T
+{
+    use x11_dl::xlib;
+    thread_local!(static XLIB: Option<xlib::Xlib> = xlib::Xlib::open().ok());
+    XLIB.with(|xlib| xlib.as_ref().map(f))
+}
+
+/// Ask the window manager to make the window fullscreen, or to restore it.
+#[cfg(target_os = "linux")]
+pub fn set_fullscreen(window: &glutin::GlWindow, fullscreen: bool) -> Result<(), &'static str> {
+    use glutin::os::unix::WindowExt;
+    use std::mem;
+    use std::os::raw::c_long;
+    use x11_dl::xlib;
+
+    let display = window.get_xlib_display().ok_or("Not an X11 window")? as *mut xlib::Display;
+    let xwindow = window.get_xlib_window().ok_or("Not an X11 window")? as xlib::Window;
+    let sent = with_xlib(|xlib| unsafe {
+        let wm_state = (xlib.XInternAtom)(display, b"_NET_WM_STATE\0".as_ptr() as *const _, 0);
+        let wm_fullscreen =
+            (xlib.XInternAtom)(display, b"_NET_WM_STATE_FULLSCREEN\0".as_ptr() as *const _, 0);
+        let mut event: xlib::XClientMessageEvent = mem::zeroed();
+        event.type_ = xlib::ClientMessage;
+        event.window = xwindow;
+        event.message_type = wm_state;
+        event.format = 32;
+        // _NET_WM_STATE_ADD or _NET_WM_STATE_REMOVE
+        event.data.set_long(0, fullscreen as c_long);
+        event.data.set_long(1, wm_fullscreen as c_long);
+        let mut event = xlib::XEvent::from(event);
+        let root = (xlib.XDefaultRootWindow)(display);
+        let mask = xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask;
+        (xlib.XSendEvent)(display, root, xlib::False, mask, &mut event);
+        (xlib.XFlush)(display);
+    });
+    sent.ok_or("Can't load Xlib")
+}
+
+/// Screen area not covered by panels, from _NET_WORKAREA, in device
+/// pixels. It spans all the monitors.
+#[cfg(target_os = "linux")]
+pub fn work_area(window: &glutin::GlWindow) -> Option<((i32, i32), (u32, u32))> {
+    use glutin::os::unix::WindowExt;
+    use std::{ptr, slice};
+    use std::os::raw::{c_int, c_long, c_uchar, c_ulong};
+    use x11_dl::xlib;
+
+    let display = match window.get_xlib_display() {
+        Some(display) => display as *mut xlib::Display,
+        None => return None,
+    };
+    let area = with_xlib(|xlib| unsafe {
+        let atom = (xlib.XInternAtom)(display,
+                                      b"_NET_WORKAREA\0".as_ptr() as *const _,
+                                      xlib::True);
+        if atom == 0 {
+            return None;
+        }
+        let root = (xlib.XDefaultRootWindow)(display);
+        let mut actual_type: xlib::Atom = 0;
+        let mut actual_format: c_int = 0;
+        let mut count: c_ulong = 0;
+        let mut remaining: c_ulong = 0;
+        let mut data: *mut c_uchar = ptr::null_mut();
+        // Only the work area of the first desktop
+        let status = (xlib.XGetWindowProperty)(display,
+                                               root,
+                                               atom,
+                                               0,
+                                               4,
+                                               xlib::False,
+                                               xlib::XA_CARDINAL,
+                                               &mut actual_type,
+                                               &mut actual_format,
+                                               &mut count,
+                                               &mut remaining,
+                                               &mut data);
+        if status != xlib::Success as c_int || data.is_null() {
+            return None;
+        }
+        let area = if actual_format == 32 && count == 4 {
+            // Format 32 properties are returned as longs
+            let values = slice::from_raw_parts(data as *const c_long, 4);
+            Some(((values[0] as i32, values[1] as i32), (values[2] as u32, values[3] as u32)))
+        } else {
+            None
+        };
+        (xlib.XFree)(data as *mut _);
+        area
+    });
+    area.and_then(|area| area)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn work_area(_window: &glutin::GlWindow) -> Option<((i32, i32), (u32, u32))> {
+    None
+}
+
+// FIXME: glutin 0.9 can only create fullscreen windows
+#[cfg(not(target_os = "linux"))]
+pub fn set_fullscreen(_window: &glutin::GlWindow, _fullscreen: bool) -> Result<(), &'static str> {
+    Err("Not supported on this platform")
+}
+
+/// Device pixels per device independent pixel, the unit of the window
+/// geometry in the shell.
+#[cfg(target_os = "windows")]
+pub fn dip_factor(_window: &glutin::GlWindow) -> f32 {
+    windows_hidpi_factor()
+}
+
+// glutin sizes are in points on Mac
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn dip_factor(_window: &glutin::GlWindow) -> f32 {
+    1.0
+}
+
+// FIXME: Wayland windows are not scaled
+#[cfg(target_os = "linux")]
+pub fn dip_factor(window: &glutin::GlWindow) -> f32 {
+    xft_dpi(window).map_or(1.0, |dpi| dpi / 96.0)
+}
+
+/// The Xft.dpi resource GTK and Qt scale with. X11 has a single value for
+/// all the monitors, read when the display is opened.
+#[cfg(target_os = "linux")]
+fn xft_dpi(window: &glutin::GlWindow) -> Option<f32> {
+    use glutin::os::unix::WindowExt;
+    use std::ffi::CStr;
+    use x11_dl::xlib;
+
+    let display = match window.get_xlib_display() {
+        Some(display) => display as *mut xlib::Display,
+        None => return None,
+    };
+    let resources = with_xlib(|xlib| unsafe {
+        let resources = (xlib.XResourceManagerString)(display);
+        if resources.is_null() {
+            None
+        } else {
+            Some(CStr::from_ptr(resources).to_string_lossy().into_owned())
+        }
+    });
+    resources
+        .and_then(|resources| resources)
+        .and_then(|resources| {
+            resources
+                .lines()
+                .filter_map(|line| {
+                    let mut split = line.splitn(2, ':');
+                    match (split.next(), split.next()) {
+                        (Some(name), Some(value)) if name.trim() == "Xft.dpi" => {
+                            value.trim().parse::<f32>().ok()
+                        }
+                        _ => None,
+                    }
+                })
+                .find(|dpi| *dpi > 0.0)
+        })
+}
+
+/// Copy with wl-copy, from wl-clipboard.
+pub fn wayland_set_clipboard(text: &str) -> Result<(), String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("wl-copy")
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Can't run wl-copy: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    match child.wait() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("wl-copy failed: {}", status)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
 #[cfg(target_os = "windows")]
 pub fn windows_hidpi_factor() -> f32 {
     use user32;
@@ -291,3 +493,92 @@ pub fn windows_hidpi_factor() -> f32 {
     let ppi = unsafe { gdi32::GetDeviceCaps(hdc, winapi::wingdi::LOGPIXELSY) };
     ppi as f32 / 96.0
 }
+
+#[cfg(test)]
+mod tests {
+    use glutin::VirtualKeyCode;
+    use super::*;
+
+    // Replays key presses and received characters the way GlutinWindow
+    // does, returning the keys reported with the characters.
+    enum Input {
+        Press(VirtualKeyCode),
+        Char(char),
+    }
+
+    fn translate(inputs: &[Input]) -> Vec<(char, Option<Key>)> {
+        let mut last_pressed_key = None;
+        let mut received = vec![];
+        for input in inputs {
+            match *input {
+                Input::Press(key_code) => {
+                    if let Ok(key) = glutin_key_to_script_key(key_code) {
+                        last_pressed_key = pressed_key_for_characters(key, key_code);
+                    }
+                }
+                Input::Char(ch) => {
+                    received.push((ch, received_character_key(ch, last_pressed_key)));
+                }
+            }
+        }
+        received
+    }
+
+    #[test]
+    fn plain_character() {
+        let received = translate(&[Input::Press(VirtualKeyCode::A), Input::Char('a')]);
+        assert_eq!(received, vec![('a', Some(Key::A))]);
+    }
+
+    #[test]
+    fn dead_key() {
+        // Dead acute, then e: the character comes after the second press
+        let received = translate(&[Input::Press(VirtualKeyCode::Apostrophe),
+                                   Input::Press(VirtualKeyCode::E),
+                                   Input::Char('é')]);
+        assert_eq!(received, vec![('é', Some(Key::E))]);
+    }
+
+    #[test]
+    fn dead_key_then_space() {
+        let received = translate(&[Input::Press(VirtualKeyCode::Grave),
+                                   Input::Press(VirtualKeyCode::Space),
+                                   Input::Char('`')]);
+        assert_eq!(received, vec![('`', Some(Key::Space))]);
+    }
+
+    #[test]
+    fn compose_sequence() {
+        // Compose, o, shift + ': shift doesn't commit anything, the
+        // character comes with the last printable key
+        let received = translate(&[Input::Press(VirtualKeyCode::Compose),
+                                   Input::Press(VirtualKeyCode::O),
+                                   Input::Press(VirtualKeyCode::LShift),
+                                   Input::Press(VirtualKeyCode::Apostrophe),
+                                   Input::Char('ö')]);
+        assert_eq!(received, vec![('ö', Some(Key::Apostrophe))]);
+    }
+
+    #[test]
+    fn input_method_commit() {
+        let received = translate(&[Input::Press(VirtualKeyCode::Space),
+                                   Input::Char('日'),
+                                   Input::Char('本')]);
+        assert_eq!(received,
+                   vec![('日', Some(Key::Space)), ('本', Some(Key::Space))]);
+    }
+
+    #[test]
+    fn non_printable_key_is_forgotten() {
+        let received = translate(&[Input::Press(VirtualKeyCode::A),
+                                   Input::Press(VirtualKeyCode::Left),
+                                   Input::Char('a')]);
+        assert_eq!(received, vec![('a', Some(Key::A))]);
+    }
+
+    #[test]
+    fn control_character_without_key() {
+        let received = translate(&[Input::Press(VirtualKeyCode::Left), Input::Char('\u{8}')]);
+        assert_eq!(received, vec![('\u{8}', None)]);
+    }
+}
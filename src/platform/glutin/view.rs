@@ -21,7 +21,14 @@ impl View {
         View { id, windows }
     }
 
-    #[cfg(not(target_os = "windows"))]
+    #[cfg(target_os = "linux")]
+    fn hidpi_factor(&self) -> f32 {
+        let windows = self.windows.borrow();
+        let win = windows.get(&self.id).unwrap();
+        super::utils::dip_factor(&win.glutin_window)
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
     fn hidpi_factor(&self) -> f32 {
         let windows = self.windows.borrow();
         let win = windows.get(&self.id).unwrap();
@@ -42,21 +49,35 @@ impl ViewMethods for View {
             .get_inner_size()
             .expect("Failed to get window inner size.");
 
-        #[cfg(target_os = "windows")]
-        let factor = super::utils::windows_hidpi_factor();
-        #[cfg(not(target_os = "windows"))]
-        let factor = 1.0;
+        // Device independent pixels
+        let factor = super::utils::dip_factor(&win.glutin_window);
+        let to_dip = |length: u32| (length as f32 / factor).round() as u32;
+        let to_dip_i32 = |coord: i32| (coord as f32 / factor).round() as i32;
 
-        width /= factor as u32;
-        height /= factor as u32;
+        width = to_dip(width);
+        height = to_dip(height);
+
+        let (x, y) = win.glutin_window
+            .get_position()
+            .expect("Failed to get window position.");
+
+        let screen_size = (to_dip(win.screen_size.0), to_dip(win.screen_size.1));
+        let (screen_avail_position, screen_avail_size) =
+            match super::utils::work_area(&win.glutin_window) {
+                Some(((x, y), (width, height))) => {
+                    ((to_dip_i32(x), to_dip_i32(y)), (to_dip(width), to_dip(height)))
+                }
+                None => ((0, 0), screen_size),
+            };
 
         DrawableGeometry {
             view_size: (width, height),
             margins: (0, 0, 0, 0),
-            position: win.glutin_window
-                .get_position()
-                .expect("Failed to get window position."),
+            position: (to_dip_i32(x), to_dip_i32(y)),
             hidpi_factor: self.hidpi_factor(),
+            screen_size,
+            screen_avail_position,
+            screen_avail_size,
         }
     }
 
@@ -69,14 +90,8 @@ impl ViewMethods for View {
         win.glutin_window.resize(w, h);
     }
 
-    // FIXME: should be controlled by state
-    fn enter_fullscreen(&self) {}
-
-    // FIXME: should be controlled by state
-    fn exit_fullscreen(&self) {}
-
     fn set_live_resize_callback(&self, _callback: &FnMut()) {
-        // FIXME
+        // Not needed, the event loop callback runs on each resize event
     }
 
     fn gl(&self) -> Rc<gl::Gl> {
@@ -9,8 +9,10 @@ use servo::EventLoopWaker;
 use state::{BrowserState, ChangeType, DiffKey, WindowState};
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::rc::Rc;
 use super::GlutinWindow;
+use super::utils;
 use tinyfiledialogs;
 use traits::view::ViewMethods;
 use traits::window::{WindowCommand, WindowEvent, WindowMethods};
@@ -71,6 +73,28 @@ impl Window {
                 .push(WindowEvent::UrlbarFocusChanged(false));
         }
     }
+
+    fn render_fullscreen(&self, state: &WindowState) {
+        let mut windows = self.windows.borrow_mut();
+        let win = windows.get_mut(&self.id).unwrap();
+        // The window manager resizes the window, which updates the geometry
+        if let Err(e) = utils::set_fullscreen(&win.glutin_window, state.fullscreen) {
+            warn!("Can't change fullscreen state: {}", e);
+            if state.fullscreen {
+                win.window_events.push(WindowEvent::DidExitFullScreen);
+            }
+        }
+    }
+
+    // FIXME: no context menu. Servo only composites one browser and we
+    // can't draw widgets over the page.
+    fn render_context_menu(&self, state: &WindowState) {
+        if state.context_menu.is_some() {
+            let mut windows = self.windows.borrow_mut();
+            let win = windows.get_mut(&self.id).unwrap();
+            win.window_events.push(WindowEvent::ContextMenuClosed);
+        }
+    }
 }
 
 impl WindowMethods for Window {
@@ -82,6 +106,8 @@ impl WindowMethods for Window {
             .expect("no current browser");
         let current_browser_state = state.tabs.ref_fg_browser().expect("no current browser");
 
+        let mut context_menu_changed = false;
+
         for change in diff {
             use self::DiffKey as K;
             match change {
@@ -94,16 +120,25 @@ impl WindowMethods for Window {
                         }
 
                         &[K::status] |
+                        &[K::logs_visible] |
+                        &[K::debug_options, _..] |
                         &[K::tabs, K::Index(_), K::Alive, K::url] |
                         &[K::tabs, K::Index(_), K::Alive, K::can_go_back] |
                         &[K::tabs, K::Index(_), K::Alive, K::can_go_forward] |
                         &[K::tabs, K::Index(_), K::Alive, K::zoom] |
+                        &[K::tabs, K::Index(_), K::Alive, K::pinch_zoom] |
                         &[K::tabs, K::Index(_), K::Alive, K::user_input] => {
                             // Nothing to do
                         }
                         &[K::tabs, K::Index(i), K::Alive, K::urlbar_focused] if i == idx => {
                             self.render_urlbar(current_browser_state);
                         }
+                        &[K::fullscreen] => {
+                            self.render_fullscreen(state);
+                        }
+                        &[K::context_menu, _..] => {
+                            context_menu_changed = true;
+                        }
                         _ => println!("Window::render: unexpected Modified keys: {:?}", keys),
                     }
                 }
@@ -112,6 +147,9 @@ impl WindowMethods for Window {
                         &[K::tabs, K::Index(_)] => {
                             self.render_title(state);
                         }
+                        &[K::context_menu, _..] => {
+                            context_menu_changed = true;
+                        }
                         _ => println!("Window::render: unexpected Added keys: {:?}", keys),
                     }
                 }
@@ -120,11 +158,18 @@ impl WindowMethods for Window {
                         &[K::tabs, K::Index(_), K::Alive] => {
                             self.render_title(state);
                         }
+                        &[K::context_menu, _..] => {
+                            context_menu_changed = true;
+                        }
                         _ => println!("Window::render: unexpected Removed keys: {:?}", keys),
                     }
                 }
             }
         }
+
+        if context_menu_changed {
+            self.render_context_menu(state);
+        }
     }
 
     fn new_view(&self) -> Result<Rc<ViewMethods>, &'static str> {
@@ -147,5 +192,27 @@ impl WindowMethods for Window {
         events
     }
 
+    // No log panel, ToggleOptionShowLogs opens servoshell://logs instead
     fn append_logs(&self, _logs: &Vec<ShellLog>) {}
+
+    // In device independent pixels, like the view geometry
+    fn set_inner_size(&self, width: u32, height: u32) {
+        let windows = self.windows.borrow();
+        let window = &windows.get(&self.id).unwrap().glutin_window;
+        let factor = utils::dip_factor(window);
+        let to_device = |length: u32| (length as f32 * factor).round() as u32;
+        window.set_inner_size(to_device(width), to_device(height));
+    }
+
+    fn set_position(&self, x: i32, y: i32) {
+        let windows = self.windows.borrow();
+        let window = &windows.get(&self.id).unwrap().glutin_window;
+        let factor = utils::dip_factor(window);
+        let to_device = |coord: i32| (coord as f32 * factor).round() as i32;
+        window.set_position(to_device(x), to_device(y));
+    }
+
+    fn choose_save_path(&self, default_name: &str) -> Option<PathBuf> {
+        tinyfiledialogs::save_file_dialog("Save", default_name).map(PathBuf::from)
+    }
 }
@@ -0,0 +1,191 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Shell preferences, in a JSON file of the profile directory. The file is
+//! watched, so edits made while ServoShell runs are applied.
+
+use serde_json;
+use servo::EventLoopWaker;
+use state::DebugOptions;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+const PREFS_FILE: &'static str = "prefs.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum StartupBehavior {
+    HomePage,
+    BlankPage,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ShellPreferences {
+    // None means the built-in home page
+    pub home_page: Option<String>,
+    // %s is replaced by the search terms
+    pub search_engine: String,
+    pub default_zoom: f32,
+    // Pixels scrolled per line, for mouse wheels that scroll by lines
+    pub line_height: f32,
+    // Otherwise, scrolling is locked to the dominant axis
+    pub diagonal_scrolling: bool,
+    pub smooth_scrolling: bool,
+    pub dark_theme: bool,
+    pub startup: StartupBehavior,
+    pub logs_visible: bool,
+    pub debug_options: DebugOptions,
+}
+
+impl Default for ShellPreferences {
+    fn default() -> ShellPreferences {
+        ShellPreferences {
+            home_page: None,
+            search_engine: "https://duckduckgo.com/html/?q=%s".to_owned(),
+            default_zoom: 1.0,
+            line_height: 38.0,
+            diagonal_scrolling: false,
+            smooth_scrolling: true,
+            dark_theme: false,
+            startup: StartupBehavior::HomePage,
+            logs_visible: false,
+            debug_options: DebugOptions::default(),
+        }
+    }
+}
+
+impl ShellPreferences {
+    pub fn search_url(&self, terms: &str) -> String {
+        self.search_engine.replace("%s", terms)
+    }
+}
+
+/// Preferences backed by a JSON file in the profile directory. In private
+/// mode, nothing is read from or written to disk.
+pub struct Preferences {
+    path: Option<PathBuf>,
+    current: ShellPreferences,
+    receiver: Option<Receiver<ShellPreferences>>,
+    // Modification time of our last write, which the watcher skips
+    written: Arc<Mutex<Option<SystemTime>>>,
+}
+
+impl Preferences {
+    pub fn load(profile: Option<&Path>) -> Preferences {
+        let path = profile.map(|p| p.join(PREFS_FILE));
+        let current = match path {
+            Some(ref path) if path.exists() => {
+                read(path).unwrap_or_else(|e| {
+                                              warn!("Can't read {}: {}", path.display(), e);
+                                              ShellPreferences::default()
+                                          })
+            }
+            _ => ShellPreferences::default(),
+        };
+        Preferences {
+            path,
+            current,
+            receiver: None,
+            written: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn get(&self) -> &ShellPreferences {
+        &self.current
+    }
+
+    /// Update and save the preferences, if anything changed.
+    pub fn set(&mut self, prefs: ShellPreferences) {
+        if prefs == self.current {
+            return;
+        }
+        self.current = prefs;
+        if let Some(ref path) = self.path {
+            let mut written = self.written.lock().unwrap();
+            match write(path, &self.current) {
+                Ok(()) => *written = modified(path),
+                Err(e) => warn!("Can't write {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    /// Watch the preferences file for changes made outside of ServoShell.
+    pub fn watch(&mut self, waker: Box<EventLoopWaker>) {
+        let path = match self.path {
+            Some(ref path) => path.clone(),
+            None => return,
+        };
+        let (sender, receiver) = channel();
+        self.receiver = Some(receiver);
+        let written = self.written.clone();
+        thread::spawn(move || {
+            let mut last_modified = modified(&path);
+            loop {
+                thread::sleep(Duration::from_secs(1));
+                // set() can't write while the lock is held
+                let last_written = written.lock().unwrap();
+                let modified = modified(&path);
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+                if modified == *last_written {
+                    continue;
+                }
+                match read(&path) {
+                    Ok(prefs) => {
+                        if sender.send(prefs).is_err() {
+                            break;
+                        }
+                        waker.wake();
+                    }
+                    Err(e) => warn!("Can't reload {}: {}", path.display(), e),
+                }
+            }
+        });
+    }
+
+    /// Preferences reloaded from disk since the last call. Returns the
+    /// new preferences if they differ from the current ones.
+    pub fn get_changes(&mut self) -> Option<ShellPreferences> {
+        let reloaded = match self.receiver {
+            Some(ref receiver) => receiver.try_iter().last(),
+            None => None,
+        };
+        match reloaded {
+            Some(prefs) if prefs != self.current => {
+                self.current = prefs.clone();
+                Some(prefs)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn read(path: &Path) -> Result<ShellPreferences, String> {
+    let mut content = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut content))
+        .map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+// Through a temporary file, so the watcher never reads a truncated file
+fn write(path: &Path, prefs: &ShellPreferences) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(prefs).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    File::create(&tmp)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .and_then(|_| fs::rename(&tmp, path))
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,226 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Remote control of a running ServoShell, enabled with `--remote`.
+//!
+//! Clients connect to a Unix domain socket and send one JSON request per
+//! line. Each request gets one JSON response per line, once it's handled.
+//! Subscribed clients also receive the window state changes as they happen.
+//!
+//! Anyone who can connect controls the browser, so the socket is only
+//! accessible to the user, in `$XDG_RUNTIME_DIR` or in the profile.
+//!
+//! Examples of requests:
+//!
+//! ```text
+//! {"WindowCommand":"Reload"}
+//! {"WindowCommand":{"Load":"https://servo.org"}}
+//! {"WindowCommand":{"SelectTab":2}}
+//! {"AppCommand":"ToggleOptionDarkTheme"}
+//! "GetWindowState"
+//! "Subscribe"
+//! ```
+
+use serde_json;
+use servo::EventLoopWaker;
+use state::{ChangeType, WindowState};
+use std::cell::RefCell;
+use std::env;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use traits::app::AppCommand;
+use traits::window::WindowCommand;
+
+#[derive(Debug, Deserialize)]
+pub enum RemoteRequest {
+    WindowCommand(WindowCommand),
+    AppCommand(AppCommand),
+    GetWindowState,
+    Subscribe,
+}
+
+#[derive(Serialize)]
+enum RemoteResponse<'a> {
+    Ok,
+    Error(String),
+    WindowState(&'a WindowState),
+    Diff(&'a [ChangeType]),
+}
+
+/// The writing end of a connection.
+pub struct RemoteClient(Box<Write + Send>);
+
+impl RemoteClient {
+    fn send(&mut self, response: &RemoteResponse) -> Result<(), ()> {
+        let mut line = serde_json::to_string(response).map_err(|_| ())?;
+        line.push('\n');
+        self.0.write_all(line.as_bytes()).map_err(|_| ())
+    }
+
+    pub fn send_ok(&mut self) {
+        self.send(&RemoteResponse::Ok).ok();
+    }
+
+    pub fn send_error(&mut self, error: String) {
+        self.send(&RemoteResponse::Error(error)).ok();
+    }
+
+    pub fn send_state(&mut self, state: &WindowState) {
+        self.send(&RemoteResponse::WindowState(state)).ok();
+    }
+}
+
+/// `servoshell.sock` in `$XDG_RUNTIME_DIR`, or else in the profile. None in
+/// private mode without `$XDG_RUNTIME_DIR`.
+pub fn socket_path(profile: Option<&Path>) -> Option<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .and_then(|dir| if dir.is_absolute() { Some(dir) } else { None })
+        .or_else(|| profile.map(|p| p.to_owned()))
+        .map(|dir| dir.join("servoshell.sock"))
+}
+
+pub struct RemoteEvent {
+    pub request: RemoteRequest,
+    pub client: RemoteClient,
+}
+
+pub struct Remote {
+    path: PathBuf,
+    receiver: Receiver<RemoteEvent>,
+    subscribers: RefCell<Vec<RemoteClient>>,
+}
+
+impl Remote {
+    pub fn new(path: PathBuf, waker: Box<EventLoopWaker>) -> Result<Remote, String> {
+        let (sender, receiver) = channel();
+        listen(&path, sender, waker)?;
+        info!("Remote control listening on {}", path.display());
+        Ok(Remote {
+               path,
+               receiver,
+               subscribers: RefCell::new(Vec::new()),
+           })
+    }
+
+    pub fn get_events(&self) -> Vec<RemoteEvent> {
+        self.receiver.try_iter().collect()
+    }
+
+    pub fn subscribe(&self, mut client: RemoteClient) {
+        client.send_ok();
+        self.subscribers.borrow_mut().push(client);
+    }
+
+    pub fn broadcast_diff(&self, diff: &[ChangeType]) {
+        if diff.is_empty() {
+            return;
+        }
+        // Disconnected subscribers are dropped
+        let mut subscribers = self.subscribers.borrow_mut();
+        let alive = subscribers
+            .drain(..)
+            .filter_map(|mut client| match client.send(&RemoteResponse::Diff(diff)) {
+                            Ok(()) => Some(client),
+                            Err(()) => None,
+                        })
+            .collect();
+        *subscribers = alive;
+    }
+
+    /// Remove the socket, so another instance can listen on it.
+    pub fn close(&self) {
+        cleanup(&self.path);
+    }
+}
+
+impl Drop for Remote {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(unix)]
+fn listen(path: &Path,
+          sender: Sender<RemoteEvent>,
+          waker: Box<EventLoopWaker>)
+          -> Result<(), String> {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::thread;
+
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!("{} is used by another instance", path.display()));
+        }
+        // Stale socket from a previous session
+        cleanup(path);
+    }
+
+    let listener = UnixListener::bind(path)
+        .map_err(|e| format!("Can't bind {}: {}", path.display(), e))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+        .map_err(|e| format!("Can't restrict {}: {}", path.display(), e))?;
+
+    thread::spawn(move || for stream in listener.incoming() {
+                      match stream {
+                          Ok(stream) => {
+                              let sender = sender.clone();
+                              let waker = waker.clone();
+                              thread::spawn(move || read_requests(stream, sender, waker));
+                          }
+                          Err(e) => warn!("Remote connection failed: {}", e),
+                      }
+                  });
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn read_requests(stream: ::std::os::unix::net::UnixStream,
+                 sender: Sender<RemoteEvent>,
+                 waker: Box<EventLoopWaker>) {
+    use std::io::{BufRead, BufReader};
+
+    let reader = match stream.try_clone() {
+        Ok(reader) => BufReader::new(reader),
+        Err(_) => return,
+    };
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut client = match stream.try_clone() {
+            Ok(writer) => RemoteClient(Box::new(writer)),
+            Err(_) => break,
+        };
+        match serde_json::from_str::<RemoteRequest>(&line) {
+            Ok(request) => {
+                if sender.send(RemoteEvent { request, client }).is_err() {
+                    break;
+                }
+                waker.wake();
+            }
+            Err(e) => client.send_error(format!("Invalid request: {}", e)),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn listen(_path: &Path,
+          _sender: Sender<RemoteEvent>,
+          _waker: Box<EventLoopWaker>)
+          -> Result<(), String> {
+    Err("Remote control is only supported on Unix".to_owned())
+}
+
+fn cleanup(path: &Path) {
+    ::std::fs::remove_file(path).ok();
+}
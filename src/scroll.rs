@@ -0,0 +1,104 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Smooth scrolling for mouse wheels that scroll by lines.
+//!
+//! Instead of jumping by a few lines at once, the distance is spread over
+//! several frames. A thread wakes up the event loop while an animation is
+//! running.
+
+use servo::EventLoopWaker;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const FRAME_MS: u64 = 16;
+// Half of the remaining distance is scrolled every HALF_LIFE_MS
+const HALF_LIFE_MS: f32 = 40.0;
+
+struct Animation {
+    x: i32,
+    y: i32,
+    remaining: (f32, f32),
+    last_tick: Instant,
+}
+
+pub struct SmoothScroll {
+    waker: Box<EventLoopWaker>,
+    animation: Option<Animation>,
+    // One per waker thread, so a thread that was stopped never outlives
+    // stop() when a new animation starts right after
+    running: Option<Arc<AtomicBool>>,
+}
+
+impl SmoothScroll {
+    pub fn new(waker: Box<EventLoopWaker>) -> SmoothScroll {
+        SmoothScroll {
+            waker,
+            animation: None,
+            running: None,
+        }
+    }
+
+    /// Scroll by (dx, dy) at (x, y), over the next frames.
+    pub fn scroll(&mut self, x: i32, y: i32, dx: f32, dy: f32) {
+        let remaining = match self.animation {
+            Some(ref animation) => (animation.remaining.0 + dx, animation.remaining.1 + dy),
+            None => (dx, dy),
+        };
+        self.animation = Some(Animation {
+                                  x,
+                                  y,
+                                  remaining,
+                                  last_tick: Instant::now(),
+                              });
+        if self.running.is_none() {
+            let running = Arc::new(AtomicBool::new(true));
+            self.running = Some(running.clone());
+            let waker = self.waker.clone();
+            thread::spawn(move || {
+                while running.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(FRAME_MS));
+                    waker.wake();
+                }
+            });
+        }
+    }
+
+    /// The scroll to perform now, if any: position and delta.
+    pub fn tick(&mut self) -> Option<(i32, i32, f32, f32)> {
+        let (step, done) = match self.animation {
+            Some(ref mut animation) => {
+                let now = Instant::now();
+                let elapsed = now.duration_since(animation.last_tick);
+                let elapsed_ms = elapsed.as_secs() as f32 * 1000.0 +
+                                 elapsed.subsec_nanos() as f32 / 1_000_000.0;
+                animation.last_tick = now;
+                let factor = 1.0 - 0.5f32.powf(elapsed_ms / HALF_LIFE_MS);
+                let (rx, ry) = animation.remaining;
+                let (mut dx, mut dy) = (rx * factor, ry * factor);
+                let done = (rx - dx).abs() < 1.0 && (ry - dy).abs() < 1.0;
+                if done {
+                    dx = rx;
+                    dy = ry;
+                }
+                animation.remaining = (rx - dx, ry - dy);
+                ((animation.x, animation.y, dx, dy), done)
+            }
+            None => return None,
+        };
+        if done {
+            self.stop();
+        }
+        Some(step)
+    }
+
+    pub fn stop(&mut self) {
+        self.animation = None;
+        if let Some(running) = self.running.take() {
+            running.store(false, Ordering::SeqCst);
+        }
+    }
+}
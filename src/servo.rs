@@ -6,6 +6,7 @@ extern crate servo;
 
 use self::servo::config::servo_version;
 use self::servo::servo_config::opts;
+use self::servo::servo_config::prefs::{PREFS, PrefValue};
 use self::servo::servo_config::resource_files::set_resources_path;
 use self::servo::compositing::windowing::{MouseWindowEvent, WindowMethods, WindowEvent};
 use self::servo::msg::constellation_msg::TraversalDirection;
@@ -13,11 +14,11 @@ use self::servo::servo_geometry::DeviceIndependentPixel;
 use self::servo::euclid::{Point2D, TypedScale, Size2D, TypedPoint2D, TypedRect, TypedSize2D,
                           TypedVector2D};
 use self::servo::ipc_channel::ipc;
-use self::servo::script_traits::{LoadData, MouseButton, TouchEventType};
+use self::servo::script_traits::{LoadData, MouseButton, TouchEventType, TouchId};
 use self::servo::style_traits::DevicePixel;
 use self::servo::net_traits::net_error_list::NetError;
 use self::servo::webrender_api;
-use state::BrowserState;
+use state::{BrowserState, DebugOptions};
 use std::path::PathBuf;
 
 pub use self::servo::BrowserId;
@@ -33,15 +34,10 @@ use traits::view::{self, DrawableGeometry};
 use std::rc::Rc;
 use std::cell::{Cell, RefCell};
 
-const SHELL_ISSUE_ALIAS: &'static str = "servoshell://issue/servoshell";
-const SERVO_ISSUE_ALIAS: &'static str = "servoshell://issue/servo";
-const SHELL_ISSUE_URL: &'static str = "http://github.com/paulrouget/servoshell/issues/new";
-const SERVO_ISSUE_URL: &'static str = "http://github.com/servo/servo/issues/new";
-
 #[derive(Debug)]
 pub enum ServoEvent {
-    SetWindowInnerSize(u32, u32),
-    SetWindowPosition(i32, i32),
+    SetWindowInnerSize(BrowserId, u32, u32),
+    SetWindowPosition(BrowserId, i32, i32),
     SetFullScreenState(bool),
     TitleChanged(BrowserId, Option<String>),
     StatusChanged(Option<String>),
@@ -52,7 +48,7 @@ pub enum ServoEvent {
     CursorChanged(ServoCursor),
     FaviconChanged(BrowserId, ServoUrl),
     Key(Option<char>, Key, KeyModifiers),
-    OpenInDefaultBrowser(&'static str),
+    InternalNavigation(BrowserId, ServoUrl),
 }
 
 struct LastMouseDown {
@@ -67,14 +63,48 @@ pub struct Servo {
     servo: RefCell<servo::Servo<ServoCallbacks>>,
     callbacks: Rc<ServoCallbacks>,
     mouse_down: RefCell<Option<LastMouseDown>>,
+    // Browser that receives the key events
+    selected_browser: Cell<Option<BrowserId>>,
 }
 
 impl Servo {
-    pub fn configure(path: PathBuf) {
+    pub fn configure(path: PathBuf,
+                     userscripts: PathBuf,
+                     profile: Option<PathBuf>,
+                     devtools_port: Option<u16>,
+                     debug: &DebugOptions) {
         let path = path.to_str().unwrap().to_string();
         set_resources_path(Some(path));
-        // FIXME: I forgot… is that necessary now?
-        opts::set_defaults(opts::default_opts());
+        let mut opts = opts::default_opts();
+        // Scripts injected in every page, like the link hints
+        opts.userscripts = userscripts.to_str().map(|s| s.to_owned());
+        // Where servo stores cookies, HSTS and auth caches
+        opts.config_dir = profile;
+        opts.devtools_port = devtools_port;
+        // Can't be changed once servo is running
+        opts.show_debug_fragment_borders = debug.show_fragment_borders;
+        opts.parallel_display_list_building = debug.parallel_display_list_building;
+        opts.show_debug_parallel_layout = debug.show_parallel_layout;
+        opts.show_debug_borders = debug.show_tiles_borders;
+        opts::set_defaults(opts);
+    }
+
+    /// Set a preference from a `name=value` string. Like servo's
+    /// `--pref`, a missing value means `true`.
+    pub fn set_pref(pref: &str) {
+        let mut split = pref.splitn(2, '=');
+        let name = split.next().unwrap();
+        let value = match split.next() {
+            Some("false") => PrefValue::Boolean(false),
+            Some("true") | None => PrefValue::Boolean(true),
+            Some(value) => {
+                match value.parse::<f64>() {
+                    Ok(v) => PrefValue::Number(v),
+                    Err(_) => PrefValue::String(value.to_owned()),
+                }
+            }
+        };
+        PREFS.set(name, value);
     }
 
     pub fn version(&self) -> String {
@@ -99,13 +129,12 @@ impl Servo {
             servo: RefCell::new(servo),
             callbacks: callbacks,
             mouse_down: RefCell::new(None),
+            selected_browser: Cell::new(None),
         }
     }
 
-    pub fn new_browser(&self, url: &str) -> BrowserState {
-
-        // FIXME: unwrap
-        let url = ServoUrl::parse(url).unwrap();
+    pub fn new_browser(&self, url: &str) -> Result<BrowserState, &'static str> {
+        let url = ServoUrl::parse(url).map_err(|_| "Invalid URL")?;
 
         let (sender, receiver) = ipc::channel().unwrap();
         self.servo
@@ -115,18 +144,20 @@ impl Servo {
         self.select_browser(id);
         self.sync(false);
 
-        BrowserState {
-            id: id,
-            is_background: true,
-            zoom: 1.0,
-            url: None,
-            title: None,
-            user_input: None,
-            can_go_back: false,
-            can_go_forward: false,
-            is_loading: false,
-            urlbar_focused: false,
-        }
+        Ok(BrowserState {
+               id: id,
+               is_background: true,
+               zoom: 1.0,
+               pinch_zoom: 1.0,
+               pinch_zoom_scroll: None,
+               url: None,
+               title: None,
+               user_input: None,
+               can_go_back: false,
+               can_go_forward: false,
+               is_loading: false,
+               urlbar_focused: false,
+           })
     }
 
     pub fn get_events(&self) -> Vec<ServoEvent> {
@@ -134,6 +165,7 @@ impl Servo {
     }
 
     pub fn select_browser(&self, id: BrowserId) {
+        self.selected_browser.set(Some(id));
         let event = WindowEvent::SelectBrowser(id);
         self.events_for_servo.borrow_mut().push(event);
     }
@@ -166,8 +198,8 @@ impl Servo {
     fn substract_margins(&self, x: i32, y: i32) -> (i32, i32) {
         let geometry = self.callbacks.geometry.get();
         let (top, _, _, left) = geometry.margins;
-        let top = top as f32 * geometry.hidpi_factor;
-        let left = left as f32 * geometry.hidpi_factor;
+        let top = (top as f32 * geometry.hidpi_factor).round();
+        let left = (left as f32 * geometry.hidpi_factor).round();
         let x = x - left as i32;
         let y = y - top as i32;
         (x, y)
@@ -188,11 +220,25 @@ impl Servo {
             view::TouchPhase::Started => TouchEventType::Down,
             view::TouchPhase::Moved => TouchEventType::Move,
             view::TouchPhase::Ended => TouchEventType::Up,
+            view::TouchPhase::Cancelled => TouchEventType::Cancel,
         };
         let event = WindowEvent::Scroll(scroll_location, TypedPoint2D::new(x, y), phase);
         self.events_for_servo.borrow_mut().push(event);
     }
 
+    pub fn perform_touch(&self, id: i32, phase: view::TouchPhase, x: i32, y: i32) {
+        let (x, y) = self.substract_margins(x, y);
+        let phase = match phase {
+            view::TouchPhase::Started => TouchEventType::Down,
+            view::TouchPhase::Moved => TouchEventType::Move,
+            view::TouchPhase::Ended => TouchEventType::Up,
+            view::TouchPhase::Cancelled => TouchEventType::Cancel,
+        };
+        let point = TypedPoint2D::new(x as f32, y as f32);
+        let event = WindowEvent::Touch(phase, TouchId(id), point);
+        self.events_for_servo.borrow_mut().push(event);
+    }
+
     pub fn update_geometry(&self, geometry: DrawableGeometry) {
         self.callbacks.geometry.set(geometry);
         self.events_for_servo
@@ -255,6 +301,17 @@ impl Servo {
             .push(WindowEvent::MouseWindowEventClass(event));
     }
 
+    /// Click at a position in CSS pixels, relative to the viewport of
+    /// a page zoomed by `zoom`.
+    pub fn perform_page_click(&self, x: f32, y: f32, zoom: f32) {
+        let geometry = self.callbacks.geometry.get();
+        let (top, _, _, left) = geometry.margins;
+        let x = ((x * zoom + left as f32) * geometry.hidpi_factor) as i32;
+        let y = ((y * zoom + top as f32) * geometry.hidpi_factor) as i32;
+        self.perform_click(x, y, view::ElementState::Pressed, view::MouseButton::Left);
+        self.perform_click(x, y, view::ElementState::Released, view::MouseButton::Left);
+    }
+
     pub fn zoom(&self, zoom: f32) {
         self.events_for_servo
             .borrow_mut()
@@ -262,6 +319,14 @@ impl Servo {
 
     }
 
+    /// Zoom the viewport, not the page. Relative to the current viewport
+    /// zoom.
+    pub fn pinch_zoom(&self, magnification: f32) {
+        self.events_for_servo
+            .borrow_mut()
+            .push(WindowEvent::PinchZoom(magnification));
+    }
+
     pub fn reset_zoom(&self) {
         // FIXME: Why is that useful? Compared to Zoom(1)
         self.events_for_servo
@@ -276,12 +341,17 @@ impl Servo {
     }
 
     pub fn send_key(&self,
-                    _id: BrowserId,
+                    id: BrowserId,
                     c: Option<char>,
                     key: Key,
                     state: KeyState,
                     mods: KeyModifiers) {
-        // FIXME: we should pass the browser id
+        // Servo's key events have no browser id. They go to the focused
+        // document of the selected browser.
+        if self.selected_browser.get() != Some(id) {
+            warn!("Dropping key event for a browser that is not selected");
+            return;
+        }
         self.events_for_servo
             .borrow_mut()
             .push(WindowEvent::KeyEvent(c, key, state, mods));
@@ -297,6 +367,11 @@ impl Servo {
     }
 }
 
+// Scale factors can be fractional (1.25, 1.5…)
+fn to_device_pixels(length: u32, scale_factor: f32) -> u32 {
+    (length as f32 * scale_factor).round() as u32
+}
+
 struct ServoCallbacks {
     pub geometry: Cell<DrawableGeometry>,
     event_queue: RefCell<Vec<ServoEvent>>,
@@ -319,17 +394,16 @@ impl WindowMethods for ServoCallbacks {
     }
 
     fn supports_clipboard(&self) -> bool {
-        // FIXME
-        false
+        // Servo reads and writes the system clipboard itself, embedders
+        // can't provide its content. The shell's clipboard is only used
+        // for its own copy commands.
+        true
     }
 
-    fn allow_navigation(&self, _id: BrowserId, url: ServoUrl, chan: ipc::IpcSender<bool>) {
-        if url.as_str() == SHELL_ISSUE_ALIAS {
-            let event = ServoEvent::OpenInDefaultBrowser(SHELL_ISSUE_URL);
-            self.event_queue.borrow_mut().push(event);
-            chan.send(false).ok();
-        } else if url.as_str() == SERVO_ISSUE_ALIAS {
-            let event = ServoEvent::OpenInDefaultBrowser(SERVO_ISSUE_URL);
+    fn allow_navigation(&self, id: BrowserId, url: ServoUrl, chan: ipc::IpcSender<bool>) {
+        // Routed by the shell, see pages.rs
+        if url.scheme() == "servoshell" {
+            let event = ServoEvent::InternalNavigation(id, url);
             self.event_queue.borrow_mut().push(event);
             chan.send(false).ok();
         } else {
@@ -351,30 +425,31 @@ impl WindowMethods for ServoCallbacks {
     }
 
     fn framebuffer_size(&self) -> TypedSize2D<u32, DevicePixel> {
-        let scale_factor = self.geometry.get().hidpi_factor as u32;
+        let scale_factor = self.geometry.get().hidpi_factor;
         let (width, height) = self.geometry.get().view_size;
-        TypedSize2D::new(scale_factor * width, scale_factor * height)
+        TypedSize2D::new(to_device_pixels(width, scale_factor),
+                         to_device_pixels(height, scale_factor))
     }
 
-    fn screen_size(&self, id: BrowserId) -> Size2D<u32> {
-        // FIXME
-        self.client_window(id).0
+    fn screen_size(&self, _id: BrowserId) -> Size2D<u32> {
+        let (width, height) = self.geometry.get().screen_size;
+        Size2D::new(width, height)
     }
 
-    fn screen_avail_size(&self, id: BrowserId) -> Size2D<u32> {
-        // FIXME
-        self.screen_size(id)
+    fn screen_avail_size(&self, _id: BrowserId) -> Size2D<u32> {
+        let (width, height) = self.geometry.get().screen_avail_size;
+        Size2D::new(width, height)
     }
 
     fn window_rect(&self) -> TypedRect<u32, DevicePixel> {
-        let scale_factor = self.geometry.get().hidpi_factor as u32;
+        let scale_factor = self.geometry.get().hidpi_factor;
         let mut size = self.framebuffer_size();
 
         let (top, right, bottom, left) = self.geometry.get().margins;
-        let top = top * scale_factor;
-        let right = right * scale_factor;
-        let bottom = bottom * scale_factor;
-        let left = left * scale_factor;
+        let top = to_device_pixels(top, scale_factor);
+        let right = to_device_pixels(right, scale_factor);
+        let bottom = to_device_pixels(bottom, scale_factor);
+        let left = to_device_pixels(left, scale_factor);
 
         size.height = size.height - top - bottom;
         size.width = size.width - left - right;
@@ -395,16 +470,16 @@ impl WindowMethods for ServoCallbacks {
 
     // Events
 
-    fn set_inner_size(&self, _id: BrowserId, size: Size2D<u32>) {
+    fn set_inner_size(&self, id: BrowserId, size: Size2D<u32>) {
         self.event_queue
             .borrow_mut()
-            .push(ServoEvent::SetWindowInnerSize(size.width as u32, size.height as u32));
+            .push(ServoEvent::SetWindowInnerSize(id, size.width as u32, size.height as u32));
     }
 
-    fn set_position(&self, _id: BrowserId, point: Point2D<i32>) {
+    fn set_position(&self, id: BrowserId, point: Point2D<i32>) {
         self.event_queue
             .borrow_mut()
-            .push(ServoEvent::SetWindowPosition(point.x, point.y));
+            .push(ServoEvent::SetWindowPosition(id, point.x, point.y));
     }
 
     fn set_fullscreen_state(&self, _id: BrowserId, state: bool) {
@@ -9,6 +9,11 @@ pub struct BrowserState {
     pub id: BrowserId,
     pub is_background: bool,
     pub zoom: f32,
+    // Viewport zoom, from touch gestures
+    pub pinch_zoom: f32,
+    // Smart zoom: once the viewport zoom is applied, scroll this point of
+    // the view, in CSS pixels, to its top left corner
+    pub pinch_zoom_scroll: Option<(f32, f32)>,
     pub url: Option<String>,
     pub title: Option<String>,
     // FIXME: pub favicon: Option<>,
@@ -16,6 +21,7 @@ pub struct BrowserState {
     pub can_go_back: bool,
     pub can_go_forward: bool,
     pub is_loading: bool,
+    // If false, the keyboard goes to the page
     pub urlbar_focused: bool,
     // FIXME:
     // creation_timestamp
@@ -0,0 +1,69 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use super::browser::BrowserState;
+use traits::window::WindowCommand;
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ContextMenu {
+    pub items: Vec<ContextMenuItem>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ContextMenuItem {
+    Separator,
+    Entry {
+        label: String,
+        command: WindowCommand,
+        enabled: bool,
+    },
+}
+
+impl ContextMenu {
+    /// Build the menu for a right click in a browser, over an optional
+    /// link and an optional image.
+    pub fn new(link: Option<String>,
+               image: Option<String>,
+               browser: &BrowserState)
+               -> ContextMenu {
+        let mut items = vec![];
+        if let Some(link) = link {
+            items.push(entry("Open Link in New Tab", WindowCommand::OpenInNewTab(link.clone())));
+            items.push(entry("Copy Link Address", WindowCommand::CopyToClipboard(link)));
+            items.push(ContextMenuItem::Separator);
+        }
+        if let Some(image) = image {
+            items.push(entry("Open Image in New Tab", WindowCommand::OpenInNewTab(image.clone())));
+            items.push(entry("Copy Image Address", WindowCommand::CopyToClipboard(image.clone())));
+            items.push(entry("Save Image", WindowCommand::SaveImage(image)));
+            items.push(ContextMenuItem::Separator);
+        }
+        items.push(ContextMenuItem::Entry {
+                       label: "Back".to_owned(),
+                       command: WindowCommand::NavigateBack,
+                       enabled: browser.can_go_back,
+                   });
+        items.push(ContextMenuItem::Entry {
+                       label: "Forward".to_owned(),
+                       command: WindowCommand::NavigateForward,
+                       enabled: browser.can_go_forward,
+                   });
+        items.push(entry("Reload", WindowCommand::Reload));
+        items.push(ContextMenuItem::Separator);
+        items.push(ContextMenuItem::Entry {
+                       label: "View Page Source".to_owned(),
+                       command: WindowCommand::ViewSource,
+                       enabled: browser.url.is_some(),
+                   });
+        ContextMenu { items }
+    }
+}
+
+fn entry(label: &str, command: WindowCommand) -> ContextMenuItem {
+    ContextMenuItem::Entry {
+        label: label.to_owned(),
+        command,
+        enabled: true,
+    }
+}
@@ -4,6 +4,7 @@
 
 mod app;
 mod browser;
+mod context_menu;
 mod state;
 mod tabs;
 mod window;
@@ -11,4 +12,5 @@ mod window;
 pub use self::state::{DiffKey, ChangeType, State};
 pub use self::app::AppState;
 pub use self::browser::{BrowserState, DeadBrowserState};
-pub use self::window::WindowState;
+pub use self::context_menu::{ContextMenu, ContextMenuItem};
+pub use self::window::{DebugOptions, WindowState};
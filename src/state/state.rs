@@ -58,7 +58,7 @@ impl<'t, T> State<T>
 // FIXME: I'm not even sure everything bound to a string
 
 #[allow(non_camel_case_types)]
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum DiffKey {
     // Don't keep the string
     Unknown(String),
@@ -75,9 +75,12 @@ pub enum DiffKey {
     status,
     urlbar_focused,
     options_open,
+    fullscreen,
+    context_menu,
     title,
     id,
     zoom,
+    pinch_zoom,
     url,
     user_input,
     can_go_back,
@@ -112,8 +115,11 @@ impl DiffKey {
                     "status" => DiffKey::status,
                     "urlbar_focused" => DiffKey::urlbar_focused,
                     "options_open" => DiffKey::options_open,
+                    "fullscreen" => DiffKey::fullscreen,
+                    "context_menu" => DiffKey::context_menu,
                     "id" => DiffKey::id,
                     "zoom" => DiffKey::zoom,
+                    "pinch_zoom" => DiffKey::pinch_zoom,
                     "url" => DiffKey::url,
                     "title" => DiffKey::title,
                     "user_input" => DiffKey::user_input,
@@ -135,7 +141,7 @@ impl DiffKey {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize)]
 pub enum ChangeType {
     Removed(Vec<DiffKey>),
     Added(Vec<DiffKey>),
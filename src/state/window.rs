@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use super::context_menu::ContextMenu;
 use super::tabs::TabsState;
 
 #[derive(Clone, PartialEq, Deserialize, Serialize)]
@@ -12,6 +13,8 @@ pub struct WindowState {
     pub debug_options: DebugOptions,
     pub status: Option<String>,
     pub options_open: bool,
+    pub fullscreen: bool,
+    pub context_menu: Option<ContextMenu>,
     pub title: String,
 }
 
@@ -23,6 +26,8 @@ impl WindowState {
             logs_visible: false,
             status: None,
             options_open: false,
+            fullscreen: false,
+            context_menu: None,
             title: "ServoShell".to_owned(),
             debug_options: DebugOptions {
                 show_fragment_borders: false,
@@ -38,7 +43,7 @@ impl WindowState {
     }
 }
 
-#[derive(Clone, PartialEq, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 pub struct DebugOptions {
     pub show_fragment_borders: bool,
     pub parallel_display_list_building: bool,
@@ -51,3 +56,49 @@ pub struct DebugOptions {
     pub wr_texture_cache_debug: bool,
     pub wr_render_target_debug: bool,
 }
+
+impl DebugOptions {
+    /// Enable a layout option by its command line name.
+    pub fn enable(&mut self, name: &str) -> Result<(), ()> {
+        self.set_layout_option(name, true)
+    }
+
+    /// Change a layout option by its command line name.
+    pub fn set_layout_option(&mut self, name: &str, enabled: bool) -> Result<(), ()> {
+        match name {
+            "show-fragment-borders" => self.show_fragment_borders = enabled,
+            "parallel-display-list-building" => self.parallel_display_list_building = enabled,
+            "show-parallel-layout" => self.show_parallel_layout = enabled,
+            "show-tiles-borders" => self.show_tiles_borders = enabled,
+            _ => return Err(()),
+        }
+        Ok(())
+    }
+
+    pub fn is_layout_option_enabled(&self, name: &str) -> bool {
+        self.enabled_layout_options().contains(&name)
+    }
+
+    /// Command line names of the enabled layout options.
+    pub fn enabled_layout_options(&self) -> Vec<&'static str> {
+        let mut names = vec![];
+        if self.show_fragment_borders {
+            names.push("show-fragment-borders");
+        }
+        if self.parallel_display_list_building {
+            names.push("parallel-display-list-building");
+        }
+        if self.show_parallel_layout {
+            names.push("show-parallel-layout");
+        }
+        if self.show_tiles_borders {
+            names.push("show-tiles-borders");
+        }
+        names
+    }
+
+    /// Servo reads the layout options only once, when it starts.
+    pub fn needs_restart(&self, running: &DebugOptions) -> bool {
+        self.enabled_layout_options() != running.enabled_layout_options()
+    }
+}
@@ -0,0 +1,340 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Touch input.
+//!
+//! `TouchEmulator` converts the mouse to touch events, to test mobile pages
+//! on desktop. Used when `DebugOptions.convert_mouse_to_touch` is on.
+//! Dragging with the left button moves one finger. Dragging with Shift
+//! held moves a second finger too, mirrored around a point next to where
+//! the drag started, which emulates a pinch.
+//!
+//! `TouchGestures` recognizes pinches and double taps. The shell handles
+//! these itself to keep track of the viewport zoom of each tab. Other
+//! touches go to servo.
+
+use std::time::{Duration, Instant};
+use traits::view::{ElementState, KeyModifiers, MouseButton, TouchPhase};
+
+// Distance, in device pixels, between the pinch center and the fingers
+// when the pinch starts. Both fingers can't start at the same point.
+const PINCH_OFFSET: i32 = 50;
+
+// A tap that moves more than this, in device pixels, is a pan
+const TAP_SLOP: i32 = 20;
+const DOUBLE_TAP_DELAY_MS: u64 = 300;
+
+#[derive(Clone, Debug)]
+pub struct TouchPoint {
+    pub id: i32,
+    pub phase: TouchPhase,
+    pub x: i32,
+    pub y: i32,
+}
+
+pub struct TouchEmulator {
+    modifiers: KeyModifiers,
+    pressed: bool,
+    pinch_center: Option<(i32, i32)>,
+}
+
+impl TouchEmulator {
+    pub fn new() -> TouchEmulator {
+        TouchEmulator {
+            modifiers: KeyModifiers::empty(),
+            pressed: false,
+            pinch_center: None,
+        }
+    }
+
+    /// Modifiers of the last key event.
+    pub fn set_modifiers(&mut self, modifiers: KeyModifiers) {
+        self.modifiers = modifiers;
+    }
+
+    pub fn mouse_input(&mut self,
+                       state: ElementState,
+                       button: MouseButton,
+                       x: i32,
+                       y: i32)
+                       -> Vec<TouchPoint> {
+        if button != MouseButton::Left {
+            return vec![];
+        }
+        match state {
+            ElementState::Pressed if !self.pressed => {
+                self.pressed = true;
+                if self.modifiers.contains(KeyModifiers::SHIFT) {
+                    self.pinch_center = Some((x - PINCH_OFFSET, y));
+                }
+                self.fingers(TouchPhase::Started, x, y)
+            }
+            ElementState::Released if self.pressed => {
+                let points = self.fingers(TouchPhase::Ended, x, y);
+                self.pressed = false;
+                self.pinch_center = None;
+                points
+            }
+            _ => vec![],
+        }
+    }
+
+    pub fn mouse_moved(&mut self, x: i32, y: i32) -> Vec<TouchPoint> {
+        if self.pressed {
+            self.fingers(TouchPhase::Moved, x, y)
+        } else {
+            vec![]
+        }
+    }
+
+    fn fingers(&self, phase: TouchPhase, x: i32, y: i32) -> Vec<TouchPoint> {
+        let mut points = vec![TouchPoint {
+                                  id: 0,
+                                  phase,
+                                  x,
+                                  y,
+                              }];
+        if let Some((cx, cy)) = self.pinch_center {
+            points.push(TouchPoint {
+                            id: 1,
+                            phase,
+                            x: 2 * cx - x,
+                            y: 2 * cy - y,
+                        });
+        }
+        points
+    }
+}
+
+#[derive(Debug)]
+pub enum Gesture {
+    Touch(TouchPoint),
+    // Scale factor since the last pinch event
+    Pinch(f32),
+    DoubleTap(i32, i32),
+}
+
+pub struct TouchGestures {
+    // Fingers down: id and position
+    points: Vec<(i32, i32, i32)>,
+    pinch_distance: Option<f32>,
+    // Set once a pinch starts, until all the fingers are up
+    ignore_touches: bool,
+    tap_start: Option<(i32, i32)>,
+    last_tap: Option<(Instant, i32, i32)>,
+}
+
+impl TouchGestures {
+    pub fn new() -> TouchGestures {
+        TouchGestures {
+            points: vec![],
+            pinch_distance: None,
+            ignore_touches: false,
+            tap_start: None,
+            last_tap: None,
+        }
+    }
+
+    pub fn handle_touch(&mut self, point: TouchPoint) -> Vec<Gesture> {
+        self.handle_touch_at(point, Instant::now())
+    }
+
+    fn handle_touch_at(&mut self, point: TouchPoint, now: Instant) -> Vec<Gesture> {
+        let TouchPoint { id, x, y, .. } = point;
+        match point.phase {
+            TouchPhase::Started => {
+                self.points.retain(|&(other, _, _)| other != id);
+                self.points.push((id, x, y));
+                match self.points.len() {
+                    1 => {
+                        self.tap_start = Some((x, y));
+                        vec![Gesture::Touch(point)]
+                    }
+                    2 if !self.ignore_touches => {
+                        // Servo saw the first finger go down. Take it back.
+                        self.ignore_touches = true;
+                        self.tap_start = None;
+                        self.pinch_distance = Some(self.distance());
+                        let (first, first_x, first_y) = self.points[0];
+                        vec![Gesture::Touch(TouchPoint {
+                                                id: first,
+                                                phase: TouchPhase::Cancelled,
+                                                x: first_x,
+                                                y: first_y,
+                                            })]
+                    }
+                    _ => vec![],
+                }
+            }
+            TouchPhase::Moved => {
+                for p in self.points.iter_mut().filter(|p| p.0 == id) {
+                    *p = (id, x, y);
+                }
+                if let Some((start_x, start_y)) = self.tap_start {
+                    if (x - start_x).abs() > TAP_SLOP || (y - start_y).abs() > TAP_SLOP {
+                        self.tap_start = None;
+                    }
+                }
+                if self.points.len() == 2 {
+                    let distance = self.distance();
+                    let magnification = match self.pinch_distance {
+                        Some(old) if old > 0.0 && distance > 0.0 => distance / old,
+                        _ => 1.0,
+                    };
+                    self.pinch_distance = Some(distance);
+                    vec![Gesture::Pinch(magnification)]
+                } else if self.ignore_touches {
+                    vec![]
+                } else {
+                    vec![Gesture::Touch(point)]
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.points.retain(|&(other, _, _)| other != id);
+                if self.points.len() < 2 {
+                    self.pinch_distance = None;
+                }
+                if self.ignore_touches {
+                    if self.points.is_empty() {
+                        self.ignore_touches = false;
+                    }
+                    return vec![];
+                }
+                let mut gestures = vec![];
+                let is_tap = point.phase == TouchPhase::Ended && self.tap_start.take().is_some();
+                gestures.push(Gesture::Touch(point));
+                if is_tap {
+                    let delay = Duration::from_millis(DOUBLE_TAP_DELAY_MS);
+                    let double_tap = match self.last_tap {
+                        Some((time, last_x, last_y)) => {
+                            now.duration_since(time) < delay && (x - last_x).abs() <= TAP_SLOP &&
+                            (y - last_y).abs() <= TAP_SLOP
+                        }
+                        None => false,
+                    };
+                    if double_tap {
+                        self.last_tap = None;
+                        gestures.push(Gesture::DoubleTap(x, y));
+                    } else {
+                        self.last_tap = Some((now, x, y));
+                    }
+                }
+                gestures
+            }
+        }
+    }
+
+    fn distance(&self) -> f32 {
+        let (_, x1, y1) = self.points[0];
+        let (_, x2, y2) = self.points[1];
+        let (dx, dy) = ((x2 - x1) as f32, (y2 - y1) as f32);
+        (dx * dx + dy * dy).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use super::*;
+    use traits::view::TouchPhase;
+
+    fn point(id: i32, phase: TouchPhase, x: i32, y: i32) -> TouchPoint {
+        TouchPoint { id, phase, x, y }
+    }
+
+    // A finger going down and up at the same place, `ms` after `start`
+    fn tap(gestures: &mut TouchGestures,
+           x: i32,
+           y: i32,
+           start: Instant,
+           ms: u64)
+           -> Vec<Gesture> {
+        let time = start + Duration::from_millis(ms);
+        gestures.handle_touch_at(point(0, TouchPhase::Started, x, y), time);
+        gestures.handle_touch_at(point(0, TouchPhase::Ended, x, y), time)
+    }
+
+    fn is_double_tap(gestures: &[Gesture]) -> bool {
+        gestures.iter().any(|gesture| match *gesture {
+                                Gesture::DoubleTap(..) => true,
+                                _ => false,
+                            })
+    }
+
+    fn is_touch(gestures: &[Gesture], id: i32, phase: TouchPhase) -> bool {
+        match gestures {
+            &[Gesture::Touch(ref point)] => point.id == id && point.phase == phase,
+            _ => false,
+        }
+    }
+
+    fn pinch(gestures: &[Gesture]) -> Option<f32> {
+        match gestures {
+            &[Gesture::Pinch(magnification)] => Some(magnification),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn two_finger_pinch() {
+        let mut gestures = TouchGestures::new();
+        let start = Instant::now();
+        let mut touch = |id, phase, x, y| gestures.handle_touch_at(point(id, phase, x, y), start);
+
+        assert!(is_touch(&touch(0, TouchPhase::Started, 100, 100), 0, TouchPhase::Started));
+        // Servo saw the first finger, it's cancelled
+        assert!(is_touch(&touch(1, TouchPhase::Started, 200, 100), 0, TouchPhase::Cancelled));
+        assert_eq!(pinch(&touch(1, TouchPhase::Moved, 300, 100)), Some(2.0));
+        assert_eq!(pinch(&touch(0, TouchPhase::Moved, 0, 100)), Some(1.5));
+
+        // Until all the fingers are up, nothing goes to servo
+        assert!(touch(1, TouchPhase::Ended, 300, 100).is_empty());
+        assert!(touch(0, TouchPhase::Moved, 10, 100).is_empty());
+        assert!(touch(0, TouchPhase::Ended, 10, 100).is_empty());
+
+        assert!(is_touch(&touch(0, TouchPhase::Started, 100, 100), 0, TouchPhase::Started));
+        assert!(is_touch(&touch(0, TouchPhase::Moved, 150, 100), 0, TouchPhase::Moved));
+    }
+
+    #[test]
+    fn double_tap() {
+        let mut gestures = TouchGestures::new();
+        let start = Instant::now();
+        assert!(!is_double_tap(&tap(&mut gestures, 100, 100, start, 0)));
+        let second = tap(&mut gestures, 100 + TAP_SLOP, 100 - TAP_SLOP, start,
+                         DOUBLE_TAP_DELAY_MS - 1);
+        assert!(is_double_tap(&second));
+        // The tap itself still goes to servo
+        assert_eq!(second.len(), 2);
+        // A third tap starts over
+        assert!(!is_double_tap(&tap(&mut gestures, 100, 100, start, DOUBLE_TAP_DELAY_MS)));
+    }
+
+    #[test]
+    fn taps_too_far_apart() {
+        let mut gestures = TouchGestures::new();
+        let start = Instant::now();
+        tap(&mut gestures, 100, 100, start, 0);
+        assert!(!is_double_tap(&tap(&mut gestures, 101 + TAP_SLOP, 100, start, 100)));
+    }
+
+    #[test]
+    fn taps_too_slow() {
+        let mut gestures = TouchGestures::new();
+        let start = Instant::now();
+        tap(&mut gestures, 100, 100, start, 0);
+        assert!(!is_double_tap(&tap(&mut gestures, 100, 100, start, DOUBLE_TAP_DELAY_MS)));
+    }
+
+    #[test]
+    fn pan_is_not_a_tap() {
+        let mut gestures = TouchGestures::new();
+        let start = Instant::now();
+        tap(&mut gestures, 100, 100, start, 0);
+        gestures.handle_touch_at(point(0, TouchPhase::Started, 100, 100), start);
+        gestures.handle_touch_at(point(0, TouchPhase::Moved, 100, 101 + TAP_SLOP), start);
+        let end = gestures.handle_touch_at(point(0, TouchPhase::Ended, 100, 100), start);
+        assert!(!is_double_tap(&end));
+    }
+}
@@ -17,7 +17,7 @@ pub enum AppEvent {
 }
 
 #[allow(dead_code)]
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum AppCommand {
     ClearHistory,
     ToggleOptionDarkTheme,
@@ -30,4 +30,5 @@ pub trait AppMethods {
     fn render(&self, diff: Vec<ChangeType>, state: &AppState);
     fn get_events(&self) -> Vec<AppEvent>;
     fn run<T>(&self, callback: T) where T: FnMut();
+    fn set_clipboard(&self, text: &str) -> Result<(), String>;
 }
@@ -9,12 +9,18 @@ use std::rc::Rc;
 pub use servo::{Key, KeyState, KeyModifiers};
 pub use servo::gl;
 
+/// Sizes and positions are in device independent pixels.
 #[derive(Debug, Copy, Clone)]
 pub struct DrawableGeometry {
     pub view_size: (u32, u32),
     pub margins: (u32, u32, u32, u32),
+    // Of the window, with its decorations
     pub position: (i32, i32),
     pub hidpi_factor: f32,
+    pub screen_size: (u32, u32),
+    // Screen area not covered by docks and panels
+    pub screen_avail_position: (i32, i32),
+    pub screen_avail_size: (u32, u32),
 }
 
 /// View events
@@ -22,17 +28,25 @@ pub struct DrawableGeometry {
 #[derive(Debug, Clone)]
 pub enum ViewEvent {
     GeometryDidChange,
-    MouseWheel(MouseScrollDelta, TouchPhase),
+    // Delta, phase, and pointer position
+    MouseWheel(MouseScrollDelta, TouchPhase, i32, i32),
     MouseInput(ElementState, MouseButton, i32, i32),
     MouseMoved(i32, i32),
     KeyEvent(Option<char>, Key, KeyState, KeyModifiers),
+    // Touch id, phase, position
+    Touch(i32, TouchPhase, i32, i32),
+    // Touchpad pinch. Scale factor since the last event.
+    Magnify(f32),
+    // Touchpad double tap
+    SmartZoom(i32, i32),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TouchPhase {
     Started,
     Moved,
     Ended,
+    Cancelled,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -57,8 +71,6 @@ pub enum MouseScrollDelta {
 pub trait ViewMethods {
     fn get_geometry(&self) -> DrawableGeometry;
     fn update_drawable(&self);
-    fn enter_fullscreen(&self);
-    fn exit_fullscreen(&self);
     fn set_live_resize_callback(&self, callback: &FnMut());
     fn gl(&self) -> Rc<gl::Gl>;
     fn get_events(&self) -> Vec<ViewEvent>;
@@ -7,6 +7,7 @@
 use state::{ChangeType, WindowState};
 use traits::view::ViewMethods;
 use servo::EventLoopWaker;
+use std::path::PathBuf;
 use std::rc::Rc;
 use logs::ShellLog;
 
@@ -18,11 +19,12 @@ pub enum WindowEvent {
     DidExitFullScreen,
     WillClose,
     OptionsClosed,
+    ContextMenuClosed,
     UrlbarFocusChanged(bool),
     DoCommand(WindowCommand),
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum WindowCommand {
     Reload,
     Stop,
@@ -33,7 +35,10 @@ pub enum WindowCommand {
     ZoomIn,
     ZoomOut,
     ZoomToActualSize,
+    ResetPinchZoom,
     ToggleSidebar,
+    ExportLogs,
+    ToggleFullscreen,
     NewTab,
     CloseTab,
     NextTab,
@@ -41,6 +46,7 @@ pub enum WindowCommand {
     SelectTab(usize),
     ShowOptions,
     Load(String),
+    ToggleBookmark,
     ToggleOptionShowLogs,
     ToggleOptionFragmentBorders,
     ToggleOptionParallelDisplayListBuidling,
@@ -50,6 +56,18 @@ pub enum WindowCommand {
     ToggleOptionWRProfiler,
     ToggleOptionWRTextureCacheDebug,
     ToggleOptionWRTargetDebug,
+    ShowLinkHints(LinkHintAction),
+    OpenInNewTab(String),
+    CopyToClipboard(String),
+    SaveImage(String),
+    ViewSource,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum LinkHintAction {
+    Click,
+    OpenInNewTab,
+    CopyUrl,
 }
 
 pub trait WindowMethods {
@@ -58,4 +76,8 @@ pub trait WindowMethods {
     fn new_event_loop_waker(&self) -> Box<EventLoopWaker>;
     fn get_events(&self) -> Vec<WindowEvent>;
     fn append_logs(&self, logs: &Vec<ShellLog>);
+    fn set_inner_size(&self, width: u32, height: u32);
+    fn set_position(&self, x: i32, y: i32);
+    /// Ask the user where to save a file.
+    fn choose_save_path(&self, default_name: &str) -> Option<PathBuf>;
 }